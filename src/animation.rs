@@ -0,0 +1,57 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageFormat, ImageReader, ImageResult};
+
+/// Browsers show GIF delays this short as 100ms; do the same so such files don't race
+const MIN_DELAY_MS: u32 = 20;
+const SHORT_DELAY_MS: u32 = 100;
+
+pub struct Frame {
+    pub image: DynamicImage,
+    pub delay_ms: u32,
+}
+
+/// Decode every frame of an animated GIF, PNG (APNG) or WebP, composited to
+/// full-size images. Still images come back as a single frame.
+pub fn load_frames(file: &str) -> ImageResult<Vec<Frame>> {
+    let reader = ImageReader::open(file)?.with_guessed_format()?;
+    let frames = match reader.format() {
+        Some(ImageFormat::Gif) => GifDecoder::new(reader.into_inner())?.into_frames(),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader.into_inner())?;
+            if !decoder.is_apng()? {
+                return still(DynamicImage::from_decoder(decoder)?);
+            }
+            decoder.apng()?.into_frames()
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader.into_inner())?;
+            if !decoder.has_animation() {
+                return still(DynamicImage::from_decoder(decoder)?);
+            }
+            decoder.into_frames()
+        }
+        _ => return still(reader.decode()?),
+    };
+
+    frames
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay_ms = numer / denom.max(1);
+            Ok(Frame {
+                delay_ms: if delay_ms < MIN_DELAY_MS {
+                    SHORT_DELAY_MS
+                } else {
+                    delay_ms
+                },
+                image: DynamicImage::ImageRgba8(frame.into_buffer()),
+            })
+        })
+        .collect()
+}
+
+fn still(image: DynamicImage) -> ImageResult<Vec<Frame>> {
+    Ok(vec![Frame { image, delay_ms: 0 }])
+}
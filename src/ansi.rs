@@ -0,0 +1,459 @@
+use crate::encoder;
+use crate::grid::{format_bg_color, format_color, Attr, Cell, Grid, Rgb};
+use crate::quantize;
+use std::sync::OnceLock;
+
+pub const VALID_COLOR_DEPTHS: &[&str] = &["truecolor", "256", "16"];
+
+/// Colors a terminal is asked to show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+pub fn select_depth(name: &str) -> ColorDepth {
+    match name {
+        "truecolor" => ColorDepth::TrueColor,
+        "256" => ColorDepth::Ansi256,
+        "16" => ColorDepth::Ansi16,
+        _ => {
+            eprintln!(
+                "Warning: unknown color depth '{}', using 'truecolor'. Valid color depths: {}",
+                name,
+                VALID_COLOR_DEPTHS.join(", ")
+            );
+            ColorDepth::TrueColor
+        }
+    }
+}
+
+/// xterm's default 16 colors
+const XTERM_16: [Rgb; 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Channel values of the 6x6x6 color cube in the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Colors used for cells without an explicit color
+pub const DEFAULT_FG: Rgb = XTERM_16[7];
+pub const DEFAULT_BG: Rgb = XTERM_16[0];
+/// Bold text in the default color shows as bright white
+pub const BOLD_FG: Rgb = XTERM_16[15];
+
+/// The xterm 256-color palette: 16 base colors, a 6x6x6 cube, then 24 grays
+pub fn palette_256() -> &'static [Rgb] {
+    static PALETTE: OnceLock<Vec<Rgb>> = OnceLock::new();
+    PALETTE.get_or_init(|| {
+        let mut palette = XTERM_16.to_vec();
+        for i in 0..216 {
+            palette.push((
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            ));
+        }
+        for i in 0..24 {
+            let v = 8 + 10 * i as u8;
+            palette.push((v, v, v));
+        }
+        palette
+    })
+}
+
+/// Palette index for a color at the given depth. The 256-color mode skips the
+/// base 16, whose actual colors depend on the terminal theme.
+fn palette_index(color: Rgb, depth: ColorDepth) -> usize {
+    match depth {
+        ColorDepth::Ansi16 => quantize::nearest(&XTERM_16, color),
+        _ => 16 + quantize::nearest(&palette_256()[16..], color),
+    }
+}
+
+/// Snap a color to the nearest color the depth can show
+pub fn requantize(color: Rgb, depth: ColorDepth) -> Rgb {
+    match depth {
+        ColorDepth::TrueColor => color,
+        _ => palette_256()[palette_index(color, depth)],
+    }
+}
+
+/// SGR sequence selecting a foreground color at the given depth
+pub fn fg_code(color: Rgb, depth: ColorDepth) -> String {
+    let (r, g, b) = color;
+    match depth {
+        ColorDepth::TrueColor => format_color(r, g, b),
+        ColorDepth::Ansi256 => format!("\x1b[38;5;{}m", palette_index(color, depth)),
+        ColorDepth::Ansi16 => match palette_index(color, depth) {
+            i @ 0..=7 => format!("\x1b[{}m", 30 + i),
+            i => format!("\x1b[{}m", 90 + i - 8),
+        },
+    }
+}
+
+/// SGR sequence selecting a background color at the given depth
+pub fn bg_code(color: Rgb, depth: ColorDepth) -> String {
+    let (r, g, b) = color;
+    match depth {
+        ColorDepth::TrueColor => format_bg_color(r, g, b),
+        ColorDepth::Ansi256 => format!("\x1b[48;5;{}m", palette_index(color, depth)),
+        ColorDepth::Ansi16 => match palette_index(color, depth) {
+            i @ 0..=7 => format!("\x1b[{}m", 40 + i),
+            i => format!("\x1b[{}m", 100 + i - 8),
+        },
+    }
+}
+
+/// A color as set by SGR, resolved when a character is written because
+/// bold brightens the 8 base colors
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pen {
+    Default,
+    Indexed(u8),
+    Rgb(Rgb),
+}
+
+impl Pen {
+    fn resolve(self, bold: bool) -> Option<Rgb> {
+        match self {
+            Pen::Default => None,
+            Pen::Indexed(i) if bold && i < 8 => Some(XTERM_16[i as usize + 8]),
+            Pen::Indexed(i) => Some(palette_256()[i as usize]),
+            Pen::Rgb(c) => Some(c),
+        }
+    }
+}
+
+/// Cursor and SGR state while reading ANSI text
+struct Screen {
+    rows: Vec<Vec<Cell>>,
+    /// Length of the longest row, which every row is padded to at the end
+    width: usize,
+    x: usize,
+    y: usize,
+    saved: (usize, usize),
+    fg: Pen,
+    bg: Pen,
+    bold: bool,
+    reverse: bool,
+    wrap: Option<usize>,
+}
+
+/// Columns or rows past which art is rejected, so a stray cursor movement
+/// can't make the grid allocate without bound
+pub const MAX_SIZE: usize = 10_000;
+
+/// Most cells a parsed grid may have, about 10 megapixels' worth. Checked as
+/// the art grows, so the rows read so far stay within it too.
+const MAX_CELLS: usize = 10_000_000;
+
+impl Screen {
+    fn put(&mut self, glyph: char) -> Result<(), String> {
+        if self.wrap.is_some_and(|cols| self.x >= cols) {
+            self.x = 0;
+            self.y += 1;
+        }
+        if self.x >= MAX_SIZE || self.y >= MAX_SIZE {
+            return Err(format!(
+                "art reaches column {}, row {} (max {} of either)",
+                self.x.saturating_add(1),
+                self.y.saturating_add(1),
+                MAX_SIZE
+            ));
+        }
+        let mut fg = self.fg.resolve(self.bold);
+        let mut bg = self.bg.resolve(false);
+        if self.reverse {
+            (fg, bg) = (bg.or(Some(DEFAULT_BG)), fg.or(Some(DEFAULT_FG)));
+        }
+        let (r, g, b) = fg.unwrap_or(DEFAULT_FG);
+        let level = encoder::luma601(r as f64, g as f64, b as f64, 255.0);
+
+        let width = self.width.max(self.x + 1);
+        let height = self.rows.len().max(self.y + 1);
+        if width * height > MAX_CELLS {
+            return Err(format!(
+                "art is {}x{} cells (max {} cells)",
+                width, height, MAX_CELLS
+            ));
+        }
+        self.width = width;
+        if self.rows.len() <= self.y {
+            self.rows.resize(self.y + 1, Vec::new());
+        }
+        let row = &mut self.rows[self.y];
+        if row.len() <= self.x {
+            row.resize(self.x + 1, Cell::blank());
+        }
+        row[self.x] = Cell {
+            glyph,
+            level,
+            fg,
+            bg,
+            attr: Attr::Normal,
+        };
+        self.x += 1;
+        Ok(())
+    }
+
+    fn sgr(&mut self, params: &[usize]) {
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => {
+                    self.fg = Pen::Default;
+                    self.bg = Pen::Default;
+                    self.bold = false;
+                    self.reverse = false;
+                }
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fg = Pen::Indexed((n - 30) as u8),
+                n @ 90..=97 => self.fg = Pen::Indexed((n - 90 + 8) as u8),
+                39 => self.fg = Pen::Default,
+                n @ 40..=47 => self.bg = Pen::Indexed((n - 40) as u8),
+                n @ 100..=107 => self.bg = Pen::Indexed((n - 100 + 8) as u8),
+                49 => self.bg = Pen::Default,
+                n @ (38 | 48) => {
+                    // 38;5;n (256 colors) or 38;2;r;g;b (truecolor)
+                    let byte = |j: usize| params.get(j).copied().unwrap_or(0).min(255) as u8;
+                    let pen = match params.get(i + 1) {
+                        Some(5) => {
+                            let pen = Pen::Indexed(byte(i + 2));
+                            i += 2;
+                            pen
+                        }
+                        Some(2) => {
+                            let pen = Pen::Rgb((byte(i + 2), byte(i + 3), byte(i + 4)));
+                            i += 4;
+                            pen
+                        }
+                        _ => break,
+                    };
+                    if n == 38 {
+                        self.fg = pen;
+                    } else {
+                        self.bg = pen;
+                    }
+                }
+                _ => {} // blink, underline, fonts...
+            }
+            i += 1;
+        }
+    }
+
+    fn csi(&mut self, params: &[usize], command: char) {
+        let n = params.first().copied().unwrap_or(0).max(1);
+        match command {
+            'm' => self.sgr(params),
+            'A' => self.y = self.y.saturating_sub(n),
+            'B' => self.y = self.y.saturating_add(n),
+            'C' => self.x = self.x.saturating_add(n),
+            'D' => self.x = self.x.saturating_sub(n),
+            'H' | 'f' => {
+                self.y = params.first().copied().unwrap_or(1).saturating_sub(1);
+                self.x = params.get(1).copied().unwrap_or(1).saturating_sub(1);
+            }
+            's' => self.saved = (self.x, self.y),
+            'u' => (self.x, self.y) = self.saved,
+            _ => {} // clearing and modes don't change the art
+        }
+    }
+}
+
+/// Read ANSI art (SGR colors in 16, 256 or 24-bit form, plus cursor movement)
+/// into a cell grid. With `wrap`, lines break after that many columns, as
+/// classic 80-column .ans files expect. Art wider or taller than `MAX_SIZE`
+/// is an error.
+pub fn parse(text: &str, wrap: Option<usize>) -> Result<Grid, String> {
+    let mut screen = Screen {
+        rows: Vec::new(),
+        width: 0,
+        x: 0,
+        y: 0,
+        saved: (0, 0),
+        fg: Pen::Default,
+        bg: Pen::Default,
+        bold: false,
+        reverse: false,
+        wrap,
+    };
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut raw = String::new();
+                    let mut command = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            command = Some(c);
+                            break;
+                        }
+                        raw.push(c);
+                    }
+                    // Private modes like ESC[?25l have no effect on the art
+                    if let Some(command) = command.filter(|_| !raw.starts_with('?')) {
+                        let params: Vec<usize> = raw
+                            .split([';', ':'])
+                            .map(|p| p.parse().unwrap_or(0))
+                            .collect();
+                        screen.csi(&params, command);
+                    }
+                }
+                Some(']') => {
+                    // Operating system command: skip to BEL or ST
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' => {
+                screen.x = 0;
+                screen.y += 1;
+            }
+            '\r' => screen.x = 0,
+            '\t' => screen.x = (screen.x / 8).saturating_add(1).saturating_mul(8),
+            c if c.is_control() => {}
+            c => screen.put(c)?,
+        }
+    }
+
+    let width = screen.width;
+    let color = screen
+        .rows
+        .iter()
+        .flatten()
+        .any(|cell| cell.fg.is_some() || cell.bg.is_some());
+    let mut grid = Grid::new(width, screen.rows.len(), color);
+    for row in &screen.rows {
+        for x in 0..width {
+            grid.push(row.get(x).copied().unwrap_or_else(Cell::blank));
+        }
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain_text_pads_rows() {
+        let grid = parse("ab\nc\n", None).unwrap();
+        assert_eq!((grid.width, grid.height), (2, 2));
+        assert!(!grid.color);
+        let glyphs: String = grid.cells.iter().map(|c| c.glyph).collect();
+        assert_eq!(glyphs, "abc ");
+    }
+
+    #[test]
+    fn parse_sgr_color_forms() {
+        let grid = parse(
+            "\x1b[31ma\x1b[1mb\x1b[38;5;21mc\x1b[38;2;1;2;3;48;5;255md\x1b[0me",
+            None,
+        )
+        .unwrap();
+        let fg: Vec<_> = grid.cells.iter().map(|c| c.fg).collect();
+        assert_eq!(
+            fg,
+            vec![
+                Some((205, 0, 0)),
+                Some((255, 0, 0)), // bold brightens base colors
+                Some((0, 0, 255)),
+                Some((1, 2, 3)),
+                None,
+            ]
+        );
+        assert_eq!(grid.cells[3].bg, Some((238, 238, 238)));
+        assert_eq!(grid.cells[4].bg, None);
+    }
+
+    #[test]
+    fn parse_cursor_movement_and_wrap() {
+        let grid = parse("a\x1b[2Cb\x1b[2;1Hc", None).unwrap();
+        let glyphs: String = grid.cells.iter().map(|c| c.glyph).collect();
+        assert_eq!(glyphs, "a  bc   ");
+
+        let grid = parse("abcde", Some(2)).unwrap();
+        assert_eq!((grid.width, grid.height), (2, 3));
+    }
+
+    #[test]
+    fn parse_rejects_runaway_cursor_movement() {
+        assert!(parse("a\x1b[400000000Cb\n", None).is_err());
+        assert!(parse("\x1b[99999;99999Hx", None).is_err());
+        assert!(parse("\x1b[18446744073709551615Cx", None).is_err());
+        assert!(parse("\x1b[9999;9999Hx", None).is_err());
+        let edge = format!("\x1b[1;{}Hx", MAX_SIZE);
+        assert_eq!(parse(&edge, None).unwrap().width, MAX_SIZE);
+    }
+
+    #[test]
+    fn parse_stops_at_the_cell_budget() {
+        // Each line is 9999 cells wide, so the 1001st passes MAX_CELLS; the
+        // rest of the input is never read into rows
+        let text = "\x1b[9998Cx\n".repeat(1500);
+        let err = parse(&text, None).unwrap_err();
+        assert_eq!(err, "art is 9999x1001 cells (max 10000000 cells)");
+    }
+
+    #[test]
+    fn parse_reverse_video_swaps_colors() {
+        let grid = parse("\x1b[34;7mx", None).unwrap();
+        assert_eq!(grid.cells[0].fg, Some(DEFAULT_BG));
+        assert_eq!(grid.cells[0].bg, Some((0, 0, 238)));
+    }
+
+    #[test]
+    fn parse_skips_osc_and_private_modes() {
+        let grid = parse("\x1b]0;title\x07\x1b[?25lok", None).unwrap();
+        let glyphs: String = grid.cells.iter().map(|c| c.glyph).collect();
+        assert_eq!(glyphs, "ok");
+    }
+
+    #[test]
+    fn palette_256_layout() {
+        let palette = palette_256();
+        assert_eq!(palette.len(), 256);
+        assert_eq!(palette[16], (0, 0, 0));
+        assert_eq!(palette[231], (255, 255, 255));
+        assert_eq!(palette[232], (8, 8, 8));
+    }
+
+    #[test]
+    fn color_codes_by_depth() {
+        let orange = (255, 135, 0);
+        assert_eq!(
+            fg_code(orange, ColorDepth::TrueColor),
+            "\x1b[38;2;255;135;0m"
+        );
+        assert_eq!(fg_code(orange, ColorDepth::Ansi256), "\x1b[38;5;208m");
+        assert_eq!(bg_code((250, 0, 0), ColorDepth::Ansi16), "\x1b[101m");
+        assert_eq!(fg_code((200, 0, 0), ColorDepth::Ansi16), "\x1b[31m");
+        assert_eq!(requantize(orange, ColorDepth::Ansi16), (205, 205, 0));
+    }
+}
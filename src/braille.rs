@@ -0,0 +1,114 @@
+/// First codepoint of the Unicode braille patterns block
+const BRAILLE_BASE: u32 = 0x2800;
+
+/// North American ASCII braille, indexed by 6-dot pattern (dot n = bit n-1)
+const ASCII_BRAILLE: &[u8; 64] =
+    b" A1B'K2L@CIF/MSP\"E3H9O6R^DJG>NTQ,*5<-U8V.%[$+X!&;:4\\0Z7(_?W]#Y)=";
+
+/// Bit for the dot at (column, row) within a cell, following braille dot numbering:
+/// dots 1-3 run down the left column, 4-6 down the right, 7 and 8 form the bottom row
+pub fn dot_bit(dx: usize, dy: usize) -> u8 {
+    match (dx, dy) {
+        (0, 3) => 1 << 6,
+        (1, 3) => 1 << 7,
+        (0, dy) => 1 << dy,
+        (_, dy) => 1 << (dy + 3),
+    }
+}
+
+/// Pack a row-major dot mask into 6-dot braille cell patterns, 2 dots wide and
+/// 3 tall. Returns the patterns and the cell grid size.
+pub fn patterns(mask: &[bool], width: usize, height: usize) -> (Vec<u8>, usize, usize) {
+    let cols = width.div_ceil(2);
+    let rows = height.div_ceil(3);
+    let mut cells = vec![0u8; cols * rows];
+
+    for y in 0..height {
+        for x in 0..width {
+            if mask[y * width + x] {
+                let cell = (y / 3) * cols + x / 2;
+                cells[cell] |= dot_bit(x % 2, y % 3);
+            }
+        }
+    }
+    (cells, cols, rows)
+}
+
+pub fn to_unicode(pattern: u8) -> char {
+    char::from_u32(BRAILLE_BASE + pattern as u32).unwrap_or(' ')
+}
+
+/// The ASCII braille character for a 6-dot cell pattern
+pub fn to_ascii(pattern: u8) -> char {
+    ASCII_BRAILLE[(pattern & 0x3f) as usize] as char
+}
+
+/// Lay out 6-dot cell patterns as Braille Ready Format pages in ASCII braille:
+/// CR LF line endings, a form feed after each page, and trailing blank cells trimmed
+pub fn brf(cells: &[u8], cols: usize, page_rows: usize) -> String {
+    let mut output = String::with_capacity(cells.len() * 2);
+    let lines: Vec<&[u8]> = cells.chunks(cols.max(1)).collect();
+
+    for page in lines.chunks(page_rows.max(1)) {
+        for line in page {
+            let text: String = line.iter().map(|&p| to_ascii(p)).collect();
+            output.push_str(text.trim_end_matches(' '));
+            output.push_str("\r\n");
+        }
+        output.push('\x0c');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_numbering() {
+        // Left column: dots 1, 2, 3, 7; right column: dots 4, 5, 6, 8
+        assert_eq!(dot_bit(0, 0), 0x01);
+        assert_eq!(dot_bit(0, 2), 0x04);
+        assert_eq!(dot_bit(1, 0), 0x08);
+        assert_eq!(dot_bit(1, 2), 0x20);
+        assert_eq!(dot_bit(0, 3), 0x40);
+        assert_eq!(dot_bit(1, 3), 0x80);
+    }
+
+    #[test]
+    fn ascii_braille_letters() {
+        assert_eq!(to_ascii(0x01), 'A');
+        assert_eq!(to_ascii(0x03), 'B');
+        assert_eq!(to_ascii(0x09), 'C');
+        assert_eq!(to_ascii(0x3f), '=');
+        assert_eq!(to_ascii(0x00), ' ');
+    }
+
+    #[test]
+    fn unicode_braille_full_cell() {
+        assert_eq!(to_unicode(0xff), '⣿');
+        assert_eq!(to_unicode(0x00), '⠀');
+    }
+
+    #[test]
+    fn patterns_pack_six_dot_cells() {
+        // 3x3 mask: left column lit, plus the top-right dot of the second cell
+        #[rustfmt::skip]
+        let mask = [
+            true, false, true,
+            true, false, false,
+            true, false, false,
+        ];
+        let (cells, cols, rows) = patterns(&mask, 3, 3);
+        assert_eq!((cols, rows), (2, 1));
+        assert_eq!(cells, vec![0x07, 0x01]);
+    }
+
+    #[test]
+    fn brf_pages_and_line_endings() {
+        // Two lines per page, three lines total -> two pages
+        let cells = [0x01, 0x00, 0x03, 0x03, 0x00, 0x00];
+        let output = brf(&cells, 2, 2);
+        assert_eq!(output, "A\r\nBB\r\n\x0c\r\n\x0c");
+    }
+}
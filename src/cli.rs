@@ -1,13 +1,19 @@
-use clap::Parser;
+use crate::txtr::Threshold;
+use clap::{Parser, Subcommand};
 
 /// txtr converts images to text art
 #[derive(Parser, Debug)]
 #[command(name = "txtr")]
 #[command(version)]
 #[command(about = "Convert images to ASCII text art", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// Image file to convert
-    pub file: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Image files to convert; more than one makes a contact sheet
+    #[arg(required = true, value_name = "FILE")]
+    pub files: Vec<String>,
 
     /// Sets width number of characters to print image
     #[arg(short, long, default_value_t = 80)]
@@ -26,11 +32,38 @@ pub struct Args {
     #[arg(short, long)]
     pub print_in_order: bool,
 
-    /// Level threshold for determining when to print characters in order
-    #[arg(short, long, default_value_t = 127)]
-    pub level: usize,
+    /// Flow the text of a file through pixels above --level in reading order, like
+    /// --print-in-order with a whole document
+    #[arg(
+        long,
+        conflicts_with_all = ["blocks", "braille", "mosaic", "match_mode", "edges", "attributes"]
+    )]
+    pub text_source: Option<String>,
+
+    /// Move words that don't fit in the rest of a lit run to the next one, for --text-source
+    #[arg(long, requires = "text_source")]
+    pub keep_words: bool,
+
+    /// Collapse runs of whitespace in --text-source to single spaces
+    #[arg(long, requires = "text_source")]
+    pub collapse_whitespace: bool,
 
-    /// Encoder name: red, green, blue, alpha, luma601, luma709
+    /// Brightness threshold for two-level output (--print-in-order, --two-tone, --braille, ...):
+    /// a level (0-255), auto (Otsu's method) or adaptive (brighter than the surrounding area)
+    #[arg(short, long, default_value = "127", value_parser = parse_level)]
+    pub level: Threshold,
+
+    /// Two characters: the first for pixels above --level, the second for the rest
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "blocks", "braille", "mosaic", "match_mode", "edges", "attributes", "print_in_order",
+            "text_source", "emoji"
+        ]
+    )]
+    pub two_tone: Option<String>,
+
+    /// Encoder name: red, green, blue, alpha, luma601, luma709, cb, cr, hue, saturation, value
     #[arg(short, long, default_value = "luma601")]
     pub encoder: String,
 
@@ -58,10 +91,153 @@ pub struct Args {
     #[arg(long)]
     pub color: bool,
 
+    /// Background color per cell in character modes: none, dim (a darker tone of the cell's color
+    /// behind the glyph), only (colored spaces); implies --color
+    #[arg(
+        long,
+        default_value = "none",
+        conflicts_with_all = ["blocks", "braille", "mosaic", "match_mode", "edges"]
+    )]
+    pub bg: String,
+
+    /// Shade with dim, bold and reverse video as well as the ramp, for more gray levels without
+    /// color
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "blocks", "braille", "mosaic", "match_mode", "edges", "print_in_order", "bg"
+        ]
+    )]
+    pub attributes: bool,
+
     /// Use Unicode half-block characters for 2x vertical resolution (implies --color)
     #[arg(long)]
     pub blocks: bool,
 
+    /// Alpha below which --blocks and --format sixel leave pixels to the terminal background
+    /// (0 keeps every pixel) and --mask alpha leaves them outside the subject
+    #[arg(long, default_value_t = 128)]
+    pub alpha_threshold: u8,
+
+    /// Use Braille patterns for 2x4 dots per character (uses --dither, --level; add --color for color)
+    #[arg(long, conflicts_with = "blocks")]
+    pub braille: bool,
+
+    /// Use block mosaics with two colors per character: quadrant (2x2), sextant (2x3), octant (2x4)
+    #[arg(long, conflicts_with_all = ["blocks", "braille"])]
+    pub mosaic: Option<String>,
+
+    /// Draw edges as line art with characters that follow them (| - / \ _ ( )), leaving flat areas
+    /// blank
+    #[arg(long, conflicts_with_all = ["blocks", "braille", "mosaic", "match_mode"])]
+    pub edges: bool,
+
+    /// Trace edges as box-drawing lines for diagrams and UI screenshots: light, heavy or double
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "blocks", "braille", "mosaic", "match_mode", "edges", "bg", "attributes", "text_source",
+            "two_tone", "emoji", "mask"
+        ]
+    )]
+    pub lines: Option<String>,
+
+    /// Draw --edges line art over the usual brightness fill (uses --chars, --ramp, --dither)
+    #[arg(
+        long,
+        conflicts_with_all = ["blocks", "braille", "mosaic", "match_mode", "edges", "lines"]
+    )]
+    pub edge_overlay: bool,
+
+    /// Gradient strength an edge needs for --edges, --edge-overlay and --lines (0-255, a full
+    /// black-to-white step is 255)
+    #[arg(long, default_value_t = 64)]
+    pub edge_threshold: usize,
+
+    /// Color for --edges, --edge-overlay and --lines as hex RRGGBB (default: the image's color
+    /// under --color)
+    #[arg(long, value_parser = parse_color)]
+    pub edge_color: Option<(u8, u8, u8)>,
+
+    /// How characters are chosen: level (brightness ramp) or shape (glyph whose outline best fits
+    /// each cell, with fg/bg colors under --color)
+    #[arg(long = "match", default_value = "level", conflicts_with_all = ["blocks", "braille", "mosaic"])]
+    pub match_mode: String,
+
+    /// Candidate glyphs for --match shape, comma-separated: ascii, blocks, box
+    #[arg(long, default_value = "ascii,blocks,box")]
+    pub glyphs: String,
+
+    /// Map each pair of columns to the color emoji nearest in color, for chat posts
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "blocks", "braille", "mosaic", "match_mode", "edges", "edge_overlay", "attributes",
+            "text_source", "bg"
+        ]
+    )]
+    pub emoji: bool,
+
+    /// Split the image into a subject and the rest: a mask image (light areas are the subject),
+    /// alpha (opaque pixels) or level (pixels above --level). The subject uses the usual ramp,
+    /// encoder and color; the rest uses the --outside-* options.
+    #[arg(
+        long,
+        conflicts_with_all = ["blocks", "braille", "mosaic", "match_mode", "edges", "emoji"]
+    )]
+    pub mask: Option<String>,
+
+    /// Ramp preset outside the --mask subject: standard, dense, blocks, simple, none (blank)
+    #[arg(long, default_value = "none", requires = "mask")]
+    pub outside_ramp: String,
+
+    /// Characters outside the --mask subject (overrides --outside-ramp)
+    #[arg(long, requires = "mask")]
+    pub outside_chars: Option<String>,
+
+    /// Encoder outside the --mask subject (default: --encoder)
+    #[arg(long, requires = "mask")]
+    pub outside_encoder: Option<String>,
+
+    /// Color outside the --mask subject: on or off (default: same as the subject)
+    #[arg(long, requires = "mask", value_parser = clap::builder::BoolishValueParser::new())]
+    pub outside_color: Option<bool>,
+
+    /// Caption to render with a FIGlet font next to the art
+    #[arg(long)]
+    pub caption: Option<String>,
+
+    /// FIGlet font for --caption: block, braille, banner, or the path of an .flf file
+    #[arg(long, default_value = "block", requires = "caption")]
+    pub caption_font: String,
+
+    /// Where --caption goes: below, above, left, right
+    #[arg(long, default_value = "below", requires = "caption")]
+    pub caption_position: String,
+
+    /// Color --caption with a gradient of the image's colors, left to right
+    #[arg(long, requires = "caption")]
+    pub caption_gradient: bool,
+
+    /// Render each channel of a color space as its own labeled panel, side by side with rows
+    /// aligned: rgba, ycbcr or hsv
+    #[arg(long, conflicts_with_all = ["encoder", "blocks", "mosaic", "emoji", "grid"])]
+    pub separate: Option<String>,
+
+    /// Lay the images out as a contact sheet of COLSxROWS labeled thumbnails filling --width
+    /// (default: about square)
+    #[arg(long, value_parser = parse_size)]
+    pub grid: Option<(u32, u32)>,
+
+    /// Blank columns between contact sheet thumbnails or --separate panels (half as many blank
+    /// rows)
+    #[arg(long, default_value_t = 2)]
+    pub gutter: usize,
+
+    /// Draw a box around each contact sheet thumbnail or --separate panel
+    #[arg(long)]
+    pub border: bool,
+
     /// Character ramp preset: standard, dense, blocks, simple
     #[arg(long, default_value = "standard")]
     pub ramp: String,
@@ -69,4 +245,98 @@ pub struct Args {
     /// Apply Floyd-Steinberg dithering for better gradients
     #[arg(long)]
     pub dither: bool,
+
+    /// Output format: text, json, rust, c, python, js, escpos, brf, kitty, iterm2, sixel, html, svg, png,
+    /// asciicast, gif
+    #[arg(long, default_value = "text")]
+    pub format: String,
+
+    /// Prefix each line of text output, e.g. '# ' to paste into source comments
+    #[arg(long)]
+    pub comment_prefix: Option<String>,
+
+    /// Receipt printer width in characters for --format escpos (e.g. 32, 42, 48)
+    #[arg(long, default_value_t = 42)]
+    pub printer_width: u32,
+
+    /// Print --format escpos as a raster bit image instead of text (uses --dither, --level)
+    #[arg(long)]
+    pub raster: bool,
+
+    /// Embosser page size in braille cells for --format brf, as COLSxROWS
+    #[arg(long, default_value = "40x25", value_parser = parse_size)]
+    pub brf_page: (u32, u32),
+
+    /// Maximum palette size for --format sixel (2-256); --dither smooths the result
+    #[arg(long, default_value_t = 256)]
+    pub sixel_colors: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Convert existing ANSI or text art into another format
+    Convert(ConvertArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ConvertArgs {
+    /// ANSI or text art file to read, or - for stdin
+    pub file: String,
+
+    /// Output format: text, json, rust, c, python, js, escpos, html, svg, png, asciicast, gif
+    #[arg(long, default_value = "text")]
+    pub format: String,
+
+    /// Color depth to re-quantize to: truecolor, 256, 16
+    #[arg(long, default_value = "truecolor")]
+    pub colors: String,
+
+    /// Read the file as IBM code page 437 (assumed when it is not valid UTF-8)
+    #[arg(long)]
+    pub cp437: bool,
+
+    /// Wrap lines after this many columns (CP437 files default to 80)
+    #[arg(long)]
+    pub wrap: Option<usize>,
+}
+
+/// Parse a hex RRGGBB color, with or without a leading '#'
+fn parse_color(s: &str) -> Result<(u8, u8, u8), String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let value = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)
+        .ok_or_else(|| format!("expected a hex color like ff8800, got '{}'", s))?;
+    Ok(((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+/// Parse a --level threshold: a level, auto or adaptive
+fn parse_level(s: &str) -> Result<Threshold, String> {
+    match s {
+        "auto" => Ok(Threshold::Otsu),
+        "adaptive" => Ok(Threshold::Adaptive),
+        _ => s
+            .parse()
+            .map(Threshold::Fixed)
+            .map_err(|_| format!("expected a level, auto or adaptive, got '{}'", s)),
+    }
+}
+
+/// Parse a COLSxROWS size such as "40x25"
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let (cols, rows) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected COLSxROWS, got '{}'", s))?;
+    let cols: u32 = cols
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid columns '{}'", cols))?;
+    let rows: u32 = rows
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid rows '{}'", rows))?;
+    if cols == 0 || rows == 0 {
+        return Err("columns and rows must be at least 1".to_string());
+    }
+    Ok((cols, rows))
 }
@@ -0,0 +1,68 @@
+/// Upper half (0x80-0xFF) of IBM code page 437; the lower half matches ASCII
+const UPPER: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Encode a character as a CP437 byte, if the code page has it
+pub fn encode(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        return Some(c as u8);
+    }
+    UPPER.iter().position(|&u| u == c).map(|i| 0x80 + i as u8)
+}
+
+/// Decode CP437 bytes. Control bytes stay ASCII controls rather than the
+/// code page's graphic forms, so escape sequences and line breaks still work.
+pub fn decode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| {
+            if b < 0x80 {
+                b as char
+            } else {
+                UPPER[b as usize - 0x80]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_ascii_passthrough() {
+        assert_eq!(encode('A'), Some(b'A'));
+        assert_eq!(encode(' '), Some(b' '));
+    }
+
+    #[test]
+    fn encode_block_characters() {
+        assert_eq!(encode('░'), Some(0xB0));
+        assert_eq!(encode('▒'), Some(0xB1));
+        assert_eq!(encode('▓'), Some(0xB2));
+        assert_eq!(encode('█'), Some(0xDB));
+        assert_eq!(encode('▀'), Some(0xDF));
+    }
+
+    #[test]
+    fn encode_unmapped_character() {
+        assert_eq!(encode('松'), None);
+    }
+
+    #[test]
+    fn decode_round_trips_upper_half() {
+        let bytes: Vec<u8> = (0x80..=0xff).collect();
+        let text = decode(&bytes);
+        let encoded: Vec<u8> = text.chars().map(|c| encode(c).unwrap()).collect();
+        assert_eq!(encoded, bytes);
+        assert_eq!(decode(b"\x1b[0m\xdb"), "\x1b[0m█");
+    }
+}
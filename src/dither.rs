@@ -0,0 +1,56 @@
+/// Floyd-Steinberg error diffusion over a row-major buffer of N-channel values.
+/// `quantize` maps each value (with error already added) to its output value;
+/// the difference is spread to the unvisited neighbors.
+pub fn floyd_steinberg<const N: usize>(
+    values: &mut [[f64; N]],
+    width: usize,
+    height: usize,
+    mut quantize: impl FnMut([f64; N]) -> [f64; N],
+) {
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            let old_val = values[idx];
+            let new_val = quantize(old_val);
+            values[idx] = new_val;
+
+            // Distribute error to neighbors (Floyd-Steinberg pattern)
+            for c in 0..N {
+                let error = old_val[c] - new_val[c];
+                if x + 1 < width {
+                    values[idx + 1][c] += error * 7.0 / 16.0;
+                }
+                if y + 1 < height {
+                    if x > 0 {
+                        values[(y + 1) * width + (x - 1)][c] += error * 3.0 / 16.0;
+                    }
+                    values[(y + 1) * width + x][c] += error * 5.0 / 16.0;
+                    if x + 1 < width {
+                        values[(y + 1) * width + (x + 1)][c] += error * 1.0 / 16.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_spreads_to_neighbors() {
+        // 2x2 mid-gray quantized to black/white: error diffusion yields a mix
+        let mut values = vec![[0.5]; 4];
+        floyd_steinberg(&mut values, 2, 2, |[v]| [if v >= 0.5 { 1.0 } else { 0.0 }]);
+        let whites = values.iter().filter(|v| v[0] == 1.0).count();
+        assert!(whites > 0 && whites < 4);
+    }
+
+    #[test]
+    fn exact_values_are_untouched() {
+        let mut values = vec![[0.0, 1.0, 0.0]; 6];
+        floyd_steinberg(&mut values, 3, 2, |v| v.map(f64::round));
+        assert!(values.iter().all(|v| *v == [0.0, 1.0, 0.0]));
+    }
+}
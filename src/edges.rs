@@ -0,0 +1,205 @@
+/// Characters edge mode draws, for the JSON ramp
+pub const EDGE_CHARS: &str = "|-/\\_()";
+
+/// Which way an edge runs, from the gradient across it
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Orientation {
+    Vertical,
+    Horizontal,
+    /// Rising to the right, like '/'
+    Rising,
+    /// Falling to the right, like '\'
+    Falling,
+}
+
+struct Gradient {
+    /// Sobel magnitude scaled so a full black-to-white step is 255
+    magnitude: f64,
+    orientation: Orientation,
+}
+
+/// Sobel gradients of a row-major level grid, repeating the border pixels
+fn sobel(levels: &[usize], width: usize, height: usize) -> Vec<Gradient> {
+    let at = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        levels[y * width + x] as f64
+    };
+
+    let mut gradients = Vec::with_capacity(levels.len());
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+
+            // The edge runs across the gradient: a gradient pointing right
+            // (0 degrees) is a vertical edge. Rows count downwards, so a gradient
+            // towards the bottom right (45 degrees) is an edge rising like '/'.
+            let angle = gy.atan2(gx).to_degrees().rem_euclid(180.0);
+            let orientation = match angle {
+                a if !(22.5..157.5).contains(&a) => Orientation::Vertical,
+                a if a < 67.5 => Orientation::Rising,
+                a if a < 112.5 => Orientation::Horizontal,
+                _ => Orientation::Falling,
+            };
+            gradients.push(Gradient {
+                magnitude: gx.hypot(gy) / 4.0,
+                orientation,
+            });
+        }
+    }
+    gradients
+}
+
+/// Row-major mask of the pixels whose gradient is stronger than the threshold,
+/// before any thinning
+pub fn mask(levels: &[usize], width: usize, height: usize, threshold: usize) -> Vec<bool> {
+    sobel(levels, width, height)
+        .iter()
+        .map(|gradient| gradient.magnitude > threshold as f64)
+        .collect()
+}
+
+/// Pick a line character for every pixel on an edge stronger than the
+/// threshold; other pixels get None. Edges are thinned to the pixels whose
+/// gradient peaks across them, so lines come out one character thick.
+/// Horizontal edges sit low ('_') when the gradient is stronger below than
+/// above, and vertical edges bow into '(' or ')' between diagonals that lean
+/// opposite ways.
+pub fn glyphs(
+    levels: &[usize],
+    width: usize,
+    height: usize,
+    threshold: usize,
+) -> Vec<Option<char>> {
+    let gradients = sobel(levels, width, height);
+    let magnitude = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            return 0.0;
+        }
+        gradients[y as usize * width + x as usize].magnitude
+    };
+
+    // Non-maximum suppression: compare with both neighbors along the gradient,
+    // letting the first of two equal pixels win
+    let edge: Vec<bool> = gradients
+        .iter()
+        .enumerate()
+        .map(|(i, gradient)| {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            let (dx, dy) = match gradient.orientation {
+                Orientation::Vertical => (1, 0),
+                Orientation::Horizontal => (0, 1),
+                Orientation::Rising => (1, 1),
+                Orientation::Falling => (-1, 1),
+            };
+            gradient.magnitude > threshold as f64
+                && gradient.magnitude > magnitude(x - dx, y - dy)
+                && gradient.magnitude >= magnitude(x + dx, y + dy)
+        })
+        .collect();
+    let leans = |x: usize, y: Option<usize>, orientation: Orientation| {
+        let Some(y) = y.filter(|&y| y < height) else {
+            return false;
+        };
+        (x.saturating_sub(1)..(x + 2).min(width))
+            .map(|x| y * width + x)
+            .any(|i| edge[i] && gradients[i].orientation == orientation)
+    };
+
+    let mut glyphs = Vec::with_capacity(levels.len());
+    for (i, gradient) in gradients.iter().enumerate() {
+        if !edge[i] {
+            glyphs.push(None);
+            continue;
+        }
+        let (x, y) = (i % width, i / width);
+        let (above, below) = (y.checked_sub(1), Some(y + 1));
+        glyphs.push(Some(match gradient.orientation {
+            Orientation::Horizontal => {
+                let (x, y) = (x as isize, y as isize);
+                if magnitude(x, y + 1) > magnitude(x, y - 1) {
+                    '_'
+                } else {
+                    '-'
+                }
+            }
+            Orientation::Vertical => {
+                if leans(x, above, Orientation::Rising) && leans(x, below, Orientation::Falling) {
+                    '('
+                } else if leans(x, above, Orientation::Falling)
+                    && leans(x, below, Orientation::Rising)
+                {
+                    ')'
+                } else {
+                    '|'
+                }
+            }
+            Orientation::Rising => '/',
+            Orientation::Falling => '\\',
+        }));
+    }
+    glyphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(glyphs: &[Option<char>], width: usize) -> Vec<String> {
+        glyphs
+            .chunks(width)
+            .map(|row| row.iter().map(|g| g.unwrap_or(' ')).collect())
+            .collect()
+    }
+
+    #[test]
+    fn flat_image_has_no_edges() {
+        let levels = vec![128; 16];
+        assert!(glyphs(&levels, 4, 4, 10).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn vertical_and_horizontal_steps() {
+        // Dark left half, bright right half
+        let levels: Vec<usize> = (0..24).map(|i| if i % 6 < 3 { 0 } else { 255 }).collect();
+        let rows = render(&glyphs(&levels, 6, 4, 64), 6);
+        assert!(rows.iter().all(|row| row == "  |   "), "{:?}", rows);
+
+        // Dark top half, bright bottom half
+        let levels: Vec<usize> = (0..24).map(|i| if i / 4 < 3 { 0 } else { 255 }).collect();
+        let rows = render(&glyphs(&levels, 4, 6, 64), 4);
+        assert_eq!(rows[2], "____");
+        assert_eq!(rows[3], "    ");
+    }
+
+    #[test]
+    fn diagonal_steps() {
+        // Bright below the main diagonal: the edge falls to the right
+        let levels: Vec<usize> = (0..36)
+            .map(|i| if i / 6 > i % 6 { 255 } else { 0 })
+            .collect();
+        let rows = render(&glyphs(&levels, 6, 6, 64), 6);
+        assert_eq!(rows[3].chars().nth(3), Some('\\'), "{:?}", rows);
+
+        // Bright below the anti-diagonal: the edge rises to the right
+        let levels: Vec<usize> = (0..36)
+            .map(|i| if i / 6 + i % 6 > 5 { 255 } else { 0 })
+            .collect();
+        let rows = render(&glyphs(&levels, 6, 6, 64), 6);
+        assert_eq!(rows[3].chars().nth(2), Some('/'), "{:?}", rows);
+    }
+
+    #[test]
+    fn threshold_drops_weak_edges() {
+        let levels: Vec<usize> = (0..16).map(|i| if i % 4 < 2 { 100 } else { 130 }).collect();
+        assert!(glyphs(&levels, 4, 4, 64).iter().all(Option::is_none));
+        assert!(glyphs(&levels, 4, 4, 10).iter().any(Option::is_some));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::grid::Rgb;
+use crate::quantize;
+use std::sync::OnceLock;
+
+/// Emoji that fill most of their cell, with their average color as drawn by
+/// common emoji fonts (Twemoji's palette). Only single code points with
+/// default emoji presentation, so every terminal shows them two columns wide.
+const EMOJI: &[(char, Rgb)] = &[
+    ('🟥', (221, 46, 68)),
+    ('🟧', (244, 144, 12)),
+    ('🟨', (253, 203, 88)),
+    ('🟩', (120, 177, 89)),
+    ('🟦', (85, 172, 238)),
+    ('🟪', (170, 142, 214)),
+    ('🟫', (193, 105, 79)),
+    ('⬛', (49, 55, 61)),
+    ('⬜', (230, 231, 232)),
+    ('🌑', (102, 117, 127)),
+    ('🐘', (153, 170, 181)),
+    ('🪨', (130, 130, 125)),
+    ('🦴', (235, 226, 210)),
+    ('🌕', (255, 217, 131)),
+    ('🧈', (255, 232, 182)),
+    ('🍋', (255, 204, 77)),
+    ('🌅', (255, 172, 51)),
+    ('🍑', (255, 136, 108)),
+    ('🍞', (217, 158, 130)),
+    ('🌰', (153, 77, 43)),
+    ('🍫', (102, 33, 19)),
+    ('🍓', (190, 25, 49)),
+    ('🌺', (234, 89, 110)),
+    ('🐷', (244, 171, 186)),
+    ('🌸', (252, 210, 222)),
+    ('🍆', (116, 78, 170)),
+    ('🍇', (85, 55, 136)),
+    ('🫐', (60, 80, 150)),
+    ('🌌', (60, 50, 110)),
+    ('🌃', (48, 55, 90)),
+    ('🌊', (59, 136, 195)),
+    ('🧊', (187, 221, 245)),
+    ('🦚', (60, 150, 120)),
+    ('🌲', (62, 114, 29)),
+    ('🌳', (92, 145, 59)),
+    ('🫒', (122, 130, 52)),
+    ('🥑', (167, 196, 87)),
+    ('🍐', (166, 211, 136)),
+];
+
+/// Every emoji mode can print, for the JSON ramp
+pub fn all() -> String {
+    EMOJI.iter().map(|(c, _)| c).collect()
+}
+
+/// The emoji whose color looks closest, compared in CIELAB
+pub fn nearest(color: Rgb) -> char {
+    static LAB: OnceLock<Vec<[f64; 3]>> = OnceLock::new();
+    let table = LAB.get_or_init(|| EMOJI.iter().map(|&(_, c)| quantize::lab(c)).collect());
+
+    let target = quantize::lab(color);
+    let distance = |lab: &[f64; 3]| (0..3).map(|i| (lab[i] - target[i]).powi(2)).sum::<f64>();
+    table
+        .iter()
+        .zip(EMOJI)
+        .min_by(|(a, _), (b, _)| distance(a).total_cmp(&distance(b)))
+        .map_or(' ', |(_, &(c, _))| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_colors_match_themselves() {
+        for &(c, color) in EMOJI {
+            assert_eq!(nearest(color), c);
+        }
+    }
+
+    #[test]
+    fn nearest_by_appearance() {
+        assert_eq!(nearest((0, 0, 0)), '⬛');
+        assert_eq!(nearest((255, 255, 255)), '⬜');
+        assert_eq!(nearest((200, 0, 0)), '🍓');
+        assert_eq!(nearest((20, 90, 20)), '🌲');
+    }
+
+    #[test]
+    fn single_wide_code_points() {
+        for &(c, _) in EMOJI {
+            // Emoji presentation without a variation selector starts at U+1F000,
+            // apart from the two large squares
+            assert!(c as u32 >= 0x1F000 || c == '⬛' || c == '⬜', "{}", c);
+        }
+    }
+}
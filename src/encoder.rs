@@ -1,6 +1,20 @@
 pub type EncoderFn = Box<dyn Fn(f64, f64, f64, f64) -> usize>;
 
-pub const VALID_ENCODERS: &[&str] = &["red", "green", "blue", "alpha", "luma601", "luma709"];
+pub const VALID_ENCODERS: &[&str] = &[
+    "red",
+    "green",
+    "blue",
+    "alpha",
+    "luma601",
+    "luma709",
+    "cb",
+    "cr",
+    "hue",
+    "saturation",
+    "value",
+];
+
+pub const VALID_SEPARATIONS: &[&str] = &["rgba", "ycbcr", "hsv"];
 
 pub fn select(enc: &str) -> EncoderFn {
     let encoder = match enc {
@@ -10,6 +24,11 @@ pub fn select(enc: &str) -> EncoderFn {
         "alpha" => alpha,
         "luma601" => luma601,
         "luma709" => luma709,
+        "cb" => cb,
+        "cr" => cr,
+        "hue" => hue,
+        "saturation" => saturation,
+        "value" => value,
         _ => {
             eprintln!(
                 "Warning: unknown encoder '{}', using 'luma709'. Valid encoders: {}",
@@ -23,6 +42,23 @@ pub fn select(enc: &str) -> EncoderFn {
     Box::new(encoder)
 }
 
+/// Encoders for each channel of a color space, for --separate
+pub fn separation(space: &str) -> &'static [&'static str] {
+    match space {
+        "rgba" => &["red", "green", "blue", "alpha"],
+        "ycbcr" => &["luma601", "cb", "cr"],
+        "hsv" => &["hue", "saturation", "value"],
+        _ => {
+            eprintln!(
+                "Warning: unknown color space '{}', using 'rgba'. Valid color spaces: {}",
+                space,
+                VALID_SEPARATIONS.join(", ")
+            );
+            &["red", "green", "blue", "alpha"]
+        }
+    }
+}
+
 pub fn red(r: f64, _g: f64, _b: f64, _a: f64) -> usize {
     r as usize
 }
@@ -48,6 +84,47 @@ pub fn luma709(r: f64, g: f64, b: f64, _a: f64) -> usize {
     (r * 0.2126 + g * 0.7152 + b * 0.0722) as usize
 }
 
+/// Blue-difference chroma of JPEG's YCbCr, 128 for grays
+pub fn cb(r: f64, g: f64, b: f64, _a: f64) -> usize {
+    (128.0 - r * 0.168736 - g * 0.331264 + b * 0.5).clamp(0.0, 255.0) as usize
+}
+
+/// Red-difference chroma of JPEG's YCbCr, 128 for grays
+pub fn cr(r: f64, g: f64, b: f64, _a: f64) -> usize {
+    (128.0 + r * 0.5 - g * 0.418688 - b * 0.081312).clamp(0.0, 255.0) as usize
+}
+
+/// HSV hue, the color wheel from red around to red scaled to 0-255; 0 for grays
+pub fn hue(r: f64, g: f64, b: f64, _a: f64) -> usize {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max == min {
+        return 0;
+    }
+    let sector = if max == r {
+        (g - b) / (max - min)
+    } else if max == g {
+        2.0 + (b - r) / (max - min)
+    } else {
+        4.0 + (r - g) / (max - min)
+    };
+    (sector.rem_euclid(6.0) / 6.0 * 255.0) as usize
+}
+
+/// HSV saturation: how far the color is from gray
+pub fn saturation(r: f64, g: f64, b: f64, _a: f64) -> usize {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max == 0.0 {
+        0
+    } else {
+        ((max - min) / max * 255.0) as usize
+    }
+}
+
+/// HSV value: the brightest channel
+pub fn value(r: f64, g: f64, b: f64, _a: f64) -> usize {
+    r.max(g).max(b) as usize
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,9 +132,8 @@ mod tests {
     // select() tests
     #[test]
     fn select_valid_encoders() {
-        // All 6 valid encoder names should work without warnings
-        let encoders = ["red", "green", "blue", "alpha", "luma601", "luma709"];
-        for name in encoders {
+        // All valid encoder names should work without warnings
+        for name in VALID_ENCODERS {
             let encoder = select(name);
             // Just verify it returns a valid function by calling it
             let _ = encoder(100.0, 100.0, 100.0, 100.0);
@@ -94,7 +170,7 @@ mod tests {
         // (255,255,255,_) -> 254 or 255 (floating point precision)
         // 0.2126 + 0.7152 + 0.0722 = 1.0, but 255.0 * 1.0 may truncate to 254
         let result = luma709(255.0, 255.0, 255.0, 255.0);
-        assert!(result >= 254 && result <= 255);
+        assert!((254..=255).contains(&result));
     }
 
     #[test]
@@ -161,7 +237,7 @@ mod tests {
     // VALID_ENCODERS constant test
     #[test]
     fn valid_encoders_contains_all() {
-        assert_eq!(VALID_ENCODERS.len(), 6);
+        assert_eq!(VALID_ENCODERS.len(), 11);
         assert!(VALID_ENCODERS.contains(&"red"));
         assert!(VALID_ENCODERS.contains(&"green"));
         assert!(VALID_ENCODERS.contains(&"blue"));
@@ -169,4 +245,37 @@ mod tests {
         assert!(VALID_ENCODERS.contains(&"luma601"));
         assert!(VALID_ENCODERS.contains(&"luma709"));
     }
+
+    // Color space channels
+    #[test]
+    fn chroma_is_centered_for_grays() {
+        for level in [0.0, 100.0, 255.0] {
+            assert!((127..=128).contains(&cb(level, level, level, 255.0)));
+            assert!((127..=128).contains(&cr(level, level, level, 255.0)));
+        }
+        assert_eq!(cb(0.0, 0.0, 255.0, 255.0), 255);
+        assert_eq!(cr(255.0, 0.0, 0.0, 255.0), 255);
+    }
+
+    #[test]
+    fn hsv_channels() {
+        assert_eq!(hue(255.0, 0.0, 0.0, 255.0), 0);
+        assert_eq!(hue(0.0, 255.0, 0.0, 255.0), 85);
+        assert_eq!(hue(0.0, 0.0, 255.0, 255.0), 170);
+        assert_eq!(hue(90.0, 90.0, 90.0, 255.0), 0);
+        assert_eq!(saturation(255.0, 0.0, 0.0, 255.0), 255);
+        assert_eq!(saturation(90.0, 90.0, 90.0, 255.0), 0);
+        assert_eq!(saturation(0.0, 0.0, 0.0, 255.0), 0);
+        assert_eq!(value(10.0, 200.0, 30.0, 255.0), 200);
+    }
+
+    #[test]
+    fn separations_name_valid_encoders() {
+        for space in VALID_SEPARATIONS {
+            assert!(separation(space)
+                .iter()
+                .all(|name| VALID_ENCODERS.contains(name)));
+        }
+        assert_eq!(separation("cmyk"), separation("rgba"));
+    }
 }
@@ -0,0 +1,134 @@
+use crate::cp437;
+use crate::grid::Grid;
+
+const ESC: u8 = 0x1b;
+const GS: u8 = 0x1d;
+const LF: u8 = b'\n';
+
+/// Code page number for PC437 in ESC t
+const CODE_PAGE_PC437: u8 = 0;
+
+/// Font A is 12 dots wide, so a column of text maps to 12 raster dots
+pub const DOTS_PER_COLUMN: u32 = 12;
+
+/// Rows per GS v 0 command; many printers choke on one huge raster block
+const RASTER_BAND_ROWS: usize = 128;
+
+/// Lines fed before cutting so the art clears the cutter
+const FEED_LINES: u8 = 4;
+
+fn header() -> Vec<u8> {
+    // ESC @ (initialize), ESC t n (select character code table)
+    vec![ESC, b'@', ESC, b't', CODE_PAGE_PC437]
+}
+
+fn footer(output: &mut Vec<u8>) {
+    // ESC d n (print and feed n lines), GS V 1 (partial cut)
+    output.extend_from_slice(&[ESC, b'd', FEED_LINES, GS, b'V', 1]);
+}
+
+/// Print a rendered grid as CP437 text. Colors are dropped; characters
+/// missing from the code page print as '?'.
+pub fn text(grid: &Grid) -> Vec<u8> {
+    let mut output = header();
+    output.reserve(grid.cells.len() + grid.height + 8);
+    for row in grid.rows() {
+        for cell in row {
+            output.push(cp437::encode(cell.glyph).unwrap_or(b'?'));
+        }
+        output.push(LF);
+    }
+    footer(&mut output);
+    output
+}
+
+/// Print a dot mask (true = black dot) using the GS v 0 raster bit-image command
+pub fn raster(mask: &[bool], width: usize, height: usize) -> Vec<u8> {
+    let bytes_per_row = width.div_ceil(8);
+    let mut output = header();
+    output.reserve(bytes_per_row * height + 16);
+
+    let mut y = 0;
+    while y < height {
+        let rows = RASTER_BAND_ROWS.min(height - y);
+        // GS v 0 m xL xH yL yH, with width in bytes and height in dots
+        output.extend_from_slice(&[
+            GS,
+            b'v',
+            b'0',
+            0,
+            (bytes_per_row & 0xff) as u8,
+            (bytes_per_row >> 8) as u8,
+            (rows & 0xff) as u8,
+            (rows >> 8) as u8,
+        ]);
+        for row in y..y + rows {
+            for byte_x in 0..bytes_per_row {
+                let mut byte = 0u8;
+                for bit in 0..8 {
+                    let x = byte_x * 8 + bit;
+                    if x < width && mask[row * width + x] {
+                        byte |= 0x80 >> bit;
+                    }
+                }
+                output.push(byte);
+            }
+        }
+        y += rows;
+    }
+
+    footer(&mut output);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Cell;
+
+    #[test]
+    fn text_golden_bytes() {
+        let mut grid = Grid::new(3, 1, true);
+        for glyph in ['#', '█', '松'] {
+            grid.push(Cell {
+                glyph,
+                fg: Some((255, 0, 0)),
+                ..Cell::blank()
+            });
+        }
+        assert_eq!(
+            text(&grid),
+            vec![
+                0x1b, b'@', 0x1b, b't', 0, // init, PC437
+                b'#', 0xdb, b'?', b'\n', // row
+                0x1b, b'd', 4, 0x1d, b'V', 1, // feed, cut
+            ]
+        );
+    }
+
+    #[test]
+    fn raster_golden_bytes() {
+        // 10x2 mask: first row has dots 0 and 9, second row is empty
+        let mut mask = vec![false; 20];
+        mask[0] = true;
+        mask[9] = true;
+        assert_eq!(
+            raster(&mask, 10, 2),
+            vec![
+                0x1b, b'@', 0x1b, b't', 0, // init, PC437
+                0x1d, b'v', b'0', 0, 2, 0, 2, 0, // GS v 0: 2 bytes wide, 2 rows
+                0x80, 0x40, 0x00, 0x00, // dot data
+                0x1b, b'd', 4, 0x1d, b'V', 1, // feed, cut
+            ]
+        );
+    }
+
+    #[test]
+    fn raster_splits_tall_images_into_bands() {
+        let height = RASTER_BAND_ROWS + 1;
+        let mask = vec![true; 8 * height];
+        let output = raster(&mask, 8, height);
+        let commands = output.windows(3).filter(|w| w == b"\x1dv0").count();
+        assert_eq!(commands, 2);
+    }
+}
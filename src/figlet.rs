@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+/// Fonts bundled with txtr, generated from the 8x13 bitmap font
+const BUNDLED: &[(&str, &str)] = &[
+    ("block", include_str!("../assets/fonts/block.flf")),
+    ("braille", include_str!("../assets/fonts/braille.flf")),
+    ("banner", include_str!("../assets/fonts/banner.flf")),
+];
+
+pub const VALID_FONTS: &[&str] = &["block", "braille", "banner"];
+
+pub const VALID_POSITIONS: &[&str] = &["below", "above", "left", "right"];
+
+/// Where --caption goes relative to the art
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    Below,
+    Above,
+    Left,
+    Right,
+}
+
+pub fn select_position(name: &str) -> Position {
+    match name {
+        "below" => Position::Below,
+        "above" => Position::Above,
+        "left" => Position::Left,
+        "right" => Position::Right,
+        _ => {
+            eprintln!(
+                "Warning: unknown caption position '{}', using 'below'. Valid positions: {}",
+                name,
+                VALID_POSITIONS.join(", ")
+            );
+            Position::Below
+        }
+    }
+}
+
+/// Tallest font accepted; real fonts are a few dozen rows at most, and a
+/// bogus header shouldn't make rendering allocate without bound
+const MAX_HEIGHT: usize = 256;
+
+/// Every font has ASCII, then these in order; fonts often leave them out
+const DEUTSCH: [char; 7] = ['Ä', 'Ö', 'Ü', 'ä', 'ö', 'ü', 'ß'];
+
+/// A FIGlet font: each character a block of `height` rows
+pub struct Font {
+    height: usize,
+    /// Drawn as a space, but kept solid while letters are fitted together
+    hardblank: char,
+    /// Letters side by side as drawn, rather than moved together until they touch
+    full_width: bool,
+    glyphs: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl Font {
+    /// A bundled font by name, or else the .flf file at that path
+    pub fn load(name: &str) -> Result<Font, String> {
+        if let Some((_, source)) = BUNDLED.iter().find(|(n, _)| *n == name) {
+            return Font::parse(source);
+        }
+        let source = std::fs::read(name).map_err(|e| format!("can't read font {}: {}", name, e))?;
+        Font::parse(&String::from_utf8_lossy(&source)).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    /// Parse the text of an .flf font. Smushing fonts are fitted (kerned)
+    /// instead, which FIGlet also offers and every font supports.
+    pub fn parse(source: &str) -> Result<Font, String> {
+        let mut lines = source.lines();
+        let header = lines.next().unwrap_or_default();
+        let mut fields = header.split_whitespace();
+        let hardblank = fields
+            .next()
+            .and_then(|signature| signature.strip_prefix("flf2a"))
+            .and_then(|rest| rest.chars().next())
+            .ok_or("not a FIGlet font (no flf2a header)")?;
+        let numbers: Vec<i64> = fields.map_while(|f| f.parse().ok()).collect();
+        let (height, old_layout, comments) = match numbers[..] {
+            [height, _, _, old_layout, comments, ..] if height > 0 && comments >= 0 => {
+                (height as usize, old_layout, comments as usize)
+            }
+            _ => return Err("incomplete FIGlet header".to_string()),
+        };
+        if height > MAX_HEIGHT {
+            return Err("font height too large".to_string());
+        }
+        // The full layout, when given, says whether horizontal fitting or
+        // smushing (bits 64 and 128) is on; the old layout is -1 for neither
+        let full_width = match numbers.get(6) {
+            Some(full_layout) => full_layout & (64 | 128) == 0,
+            None => old_layout < 0,
+        };
+
+        let mut lines = lines.skip(comments);
+        let mut glyphs = HashMap::new();
+        for c in (' '..='~').chain(DEUTSCH) {
+            match read_glyph(&mut lines, height) {
+                Some(glyph) => glyphs.insert(c, glyph),
+                None if c.is_ascii() => return Err(format!("font ends before '{}'", c)),
+                None => break,
+            };
+        }
+        // Code-tagged characters follow, each after a line starting with its code
+        while let Some(tag) = lines.next() {
+            let code = tag.split_whitespace().next().and_then(parse_code);
+            let Some(glyph) = read_glyph(&mut lines, height) else {
+                break;
+            };
+            if let Some(c) = code {
+                glyphs.insert(c, glyph);
+            }
+        }
+
+        Ok(Font {
+            height,
+            hardblank,
+            full_width,
+            glyphs,
+        })
+    }
+
+    /// Render one line of text, skipping characters the font doesn't have.
+    /// Rows are padded to the same width.
+    pub fn render(&self, text: &str) -> Vec<String> {
+        let mut rows: Vec<Vec<char>> = vec![Vec::new(); self.height];
+        for glyph in text.chars().filter_map(|c| self.glyphs.get(&c)) {
+            let width = glyph.iter().map(Vec::len).max().unwrap_or(0);
+            let glyph_rows = glyph
+                .iter()
+                .map(|row| row.iter().chain([' '].iter().cycle()).take(width));
+            let line_width = rows[0].len();
+
+            // Fitting slides the letter left until some row has no gap left
+            let overlap = if self.full_width {
+                0
+            } else {
+                rows.iter()
+                    .zip(glyph_rows.clone())
+                    .map(|(row, mut glyph_row)| {
+                        let trailing = row.iter().rev().take_while(|&&c| c == ' ').count();
+                        trailing + glyph_row.position(|&c| c != ' ').unwrap_or(width)
+                    })
+                    .min()
+                    .unwrap_or(0)
+                    .min(width)
+                    .min(line_width)
+            };
+
+            for (row, glyph_row) in rows.iter_mut().zip(glyph_rows) {
+                let start = line_width - overlap;
+                for (i, &c) in glyph_row.enumerate() {
+                    match row.get_mut(start + i) {
+                        Some(existing) if c != ' ' => *existing = c,
+                        Some(_) => {}
+                        None => row.push(c),
+                    }
+                }
+            }
+        }
+
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|c| if c == self.hardblank { ' ' } else { c })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The next `height` rows of a glyph, or None if the font ends first
+fn read_glyph<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    height: usize,
+) -> Option<Vec<Vec<char>>> {
+    (0..height)
+        .map(|_| lines.next().map(strip_endmarks))
+        .collect()
+}
+
+/// A glyph row without the endmark character repeated at its end
+fn strip_endmarks(line: &str) -> Vec<char> {
+    let line = line.trim_end_matches('\r');
+    let endmark = line.chars().last();
+    line.trim_end_matches(|c| Some(c) == endmark)
+        .chars()
+        .collect()
+}
+
+/// Character code of a code tag: decimal, 0x hex or 0 octal. Negative codes
+/// are FIGlet's translation-table entries and name no character.
+fn parse_code(tag: &str) -> Option<char> {
+    let code = if let Some(hex) = tag.strip_prefix("0x").or_else(|| tag.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if tag.len() > 1 && tag.starts_with('0') {
+        u32::from_str_radix(&tag[1..], 8).ok()?
+    } else {
+        tag.parse().ok()?
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-row font with only space, 'A' and 'B' drawn, with fitting or full width layout
+    fn tiny_font(old_layout: i32) -> String {
+        let mut source = format!("flf2a$ 2 2 6 {} 1\ntest font\n", old_layout);
+        for c in (' '..='~').chain(DEUTSCH) {
+            let rows = match c {
+                ' ' => ["$@", "$@@"],
+                'A' => ["/\\ @", "|| @@"],
+                'B' => [" B@", "BB@@"],
+                _ => ["@", "@@"],
+            };
+            source.push_str(&format!("{}\n{}\n", rows[0], rows[1]));
+        }
+        source
+    }
+
+    #[test]
+    fn full_width_layout() {
+        let font = Font::parse(&tiny_font(-1)).unwrap();
+        assert_eq!(font.render("AB A"), vec!["/\\  B /\\ ", "|| BB || "]);
+    }
+
+    #[test]
+    fn fitting_moves_letters_until_they_touch() {
+        let font = Font::parse(&tiny_font(0)).unwrap();
+        // The hardblank keeps its column; other blanks are squeezed out
+        assert_eq!(font.render("AB A"), vec!["/\\ B /\\ ", "||BB || "]);
+    }
+
+    #[test]
+    fn missing_characters_are_skipped() {
+        let font = Font::parse(&tiny_font(-1)).unwrap();
+        assert_eq!(font.render("A松A"), font.render("AA"));
+    }
+
+    #[test]
+    fn code_tagged_characters() {
+        let source = tiny_font(-1) + "0x263A smiley\n:)@\n()@@\n-5 skipped\nxx@\nxx@@\n";
+        let font = Font::parse(&source).unwrap();
+        assert_eq!(font.render("☺"), vec![":)", "()"]);
+        assert_eq!(parse_code("0101"), Some('A'));
+        assert_eq!(parse_code("65"), Some('A'));
+        assert_eq!(parse_code("-5"), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Font::parse("P1\n1 1\n0\n").is_err());
+        assert!(Font::parse("flf2a$ 2 2 6 -1 0\n$@\n$@@\n").is_err());
+    }
+
+    #[test]
+    fn rejects_huge_heights() {
+        let font = Font::parse("flf2a$ 999999999999 1 1 -1 0\n");
+        assert_eq!(font.err().as_deref(), Some("font height too large"));
+    }
+
+    #[test]
+    fn bundled_fonts_cover_ascii() {
+        for name in VALID_FONTS {
+            let font = Font::load(name).unwrap();
+            for c in (' '..='~').chain(DEUTSCH) {
+                assert!(font.glyphs.contains_key(&c), "{} lacks {}", name, c);
+            }
+            let rows = font.render("Hi!");
+            assert_eq!(rows.len(), font.height);
+            assert!(rows
+                .iter()
+                .all(|row| row.chars().count() == rows[0].chars().count()));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::mosaic::Mosaic;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Public domain X11 misc-fixed 8x13 font, subset to the characters txtr prints
+const FIXED_8X13: &str = include_str!("../assets/fixed-8x13.bdf");
+
+pub const GLYPH_WIDTH: u32 = 8;
+pub const GLYPH_HEIGHT: u32 = 13;
+
+pub const VALID_GLYPH_SETS: &[&str] = &["ascii", "blocks", "box"];
+
+/// One glyph: a row per scanline, most significant bit on the left
+pub type Bitmap = [u8; GLYPH_HEIGHT as usize];
+
+/// Parse the glyphs of a BDF font whose characters all fill the 8x13 cell
+fn parse_bdf(source: &str) -> HashMap<char, Bitmap> {
+    let mut glyphs = HashMap::new();
+    let mut lines = source.lines();
+    let mut encoding = None;
+
+    while let Some(line) = lines.next() {
+        if let Some(code) = line.strip_prefix("ENCODING ") {
+            encoding = code.trim().parse().ok().and_then(char::from_u32);
+        } else if line == "BITMAP" {
+            let mut bitmap = [0u8; GLYPH_HEIGHT as usize];
+            for row in bitmap.iter_mut() {
+                let hex = lines.next().unwrap_or_default();
+                *row = u8::from_str_radix(hex.trim(), 16).unwrap_or(0);
+            }
+            if let Some(c) = encoding.take() {
+                glyphs.insert(c, bitmap);
+            }
+        }
+    }
+    glyphs
+}
+
+/// Braille patterns are generated rather than stored: two columns of round-ish dots
+fn braille(pattern: u8) -> Bitmap {
+    let mut bitmap = [0u8; GLYPH_HEIGHT as usize];
+    // Dot rows 0-2 are dots 1-3 / 4-6, row 3 is dots 7 / 8
+    let bits = [(0x01, 0x08), (0x02, 0x10), (0x04, 0x20), (0x40, 0x80)];
+    for (dy, (left, right)) in bits.iter().enumerate() {
+        let y = 1 + dy * 3;
+        let mut row = 0u8;
+        if pattern & left != 0 {
+            row |= 0b0110_0000;
+        }
+        if pattern & right != 0 {
+            row |= 0b0000_0110;
+        }
+        bitmap[y] = row;
+        bitmap[y + 1] = row;
+    }
+    bitmap
+}
+
+/// Sextants and octants are likewise generated: each sub-pixel is a solid
+/// rectangle, half the cell wide and a third or quarter of it tall
+fn mosaic(c: char) -> Option<Bitmap> {
+    let (mosaic, pattern) = [Mosaic::Sextant, Mosaic::Octant]
+        .into_iter()
+        .find_map(|m| m.pattern(c).map(|p| (m, p)))?;
+    let rows = mosaic.cell_size().1;
+    let mut bitmap = [0u8; GLYPH_HEIGHT as usize];
+    for (y, row) in bitmap.iter_mut().enumerate() {
+        let sub_row = y * rows / GLYPH_HEIGHT as usize;
+        let bits = pattern >> (sub_row * 2);
+        if bits & 1 != 0 {
+            *row |= 0xf0;
+        }
+        if bits & 2 != 0 {
+            *row |= 0x0f;
+        }
+    }
+    Some(bitmap)
+}
+
+/// Look up the bitmap for a character; unknown characters draw as '?'
+pub fn glyph(c: char) -> Bitmap {
+    static GLYPHS: OnceLock<HashMap<char, Bitmap>> = OnceLock::new();
+    let glyphs = GLYPHS.get_or_init(|| parse_bdf(FIXED_8X13));
+
+    if let Some(pattern) = (c as u32).checked_sub(0x2800).filter(|&p| p <= 0xff) {
+        return braille(pattern as u8);
+    }
+    if let Some(&bitmap) = glyphs.get(&c) {
+        return bitmap;
+    }
+    mosaic(c)
+        .or_else(|| glyphs.get(&'?').copied())
+        .unwrap_or_default()
+}
+
+/// Whether the pixel at (x, y) of a bitmap is set
+pub fn lit(bitmap: &Bitmap, x: usize, y: usize) -> bool {
+    bitmap[y] & (0x80 >> x) != 0
+}
+
+/// Characters of comma-separated glyph set names (ascii, blocks, box), with
+/// their bitmaps. Unknown names warn and are skipped.
+pub fn glyph_set(names: &str) -> Vec<(char, Bitmap)> {
+    let mut chars = Vec::new();
+    for name in names.split(',').map(str::trim) {
+        match name {
+            "ascii" => chars.extend(' '..='~'),
+            "blocks" => chars.extend('\u{2580}'..='\u{259f}'),
+            "box" => chars.extend('\u{2500}'..='\u{257f}'),
+            _ => eprintln!(
+                "Warning: unknown glyph set '{}'. Valid glyph sets: {}",
+                name,
+                VALID_GLYPH_SETS.join(", ")
+            ),
+        }
+    }
+    chars.into_iter().map(|c| (c, glyph(c))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_is_empty_and_full_block_is_solid() {
+        assert_eq!(glyph(' '), [0; GLYPH_HEIGHT as usize]);
+        assert_eq!(glyph('█'), [0xff; GLYPH_HEIGHT as usize]);
+    }
+
+    #[test]
+    fn covers_box_drawing_and_cp437() {
+        for c in ['─', '╬', '▀', '░', 'Ç', 'π', '≡'] {
+            assert_ne!(glyph(c), glyph('?'), "missing glyph for {}", c);
+        }
+    }
+
+    #[test]
+    fn unknown_characters_draw_question_mark() {
+        assert_eq!(glyph('松'), glyph('?'));
+    }
+
+    #[test]
+    fn mosaic_sub_pixels() {
+        // Sextant with only the bottom-right sub-pixel: rows 8..13, right half
+        let bitmap = glyph(Mosaic::Sextant.glyph(0b10_0000));
+        assert_eq!(bitmap[7], 0);
+        assert_eq!(bitmap[9], 0x0f);
+        // Octant top row, left half
+        let bitmap = glyph(Mosaic::Octant.glyph(0b1));
+        assert_eq!(bitmap[0], 0xf0);
+        assert_eq!(bitmap[4], 0);
+    }
+
+    #[test]
+    fn glyph_sets() {
+        assert_eq!(glyph_set("ascii").len(), 95);
+        assert_eq!(glyph_set("blocks, box").len(), 32 + 128);
+        assert_eq!(glyph_set("ascii,emoji").len(), 95);
+        let (c, bitmap) = glyph_set("blocks")[0];
+        assert_eq!(c, '▀');
+        assert!(lit(&bitmap, 0, 0) && !lit(&bitmap, 7, 12));
+    }
+
+    #[test]
+    fn braille_dots() {
+        assert_eq!(glyph('⠀'), [0; GLYPH_HEIGHT as usize]);
+        let full = glyph('⣿');
+        assert_eq!(full[1], 0b0110_0110);
+        assert_eq!(full[11], 0b0110_0110);
+    }
+}
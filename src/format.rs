@@ -0,0 +1,551 @@
+use crate::ansi::{DEFAULT_BG, DEFAULT_FG};
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::grid::{Cell, Grid, Rgb};
+
+pub const VALID_FORMATS: &[&str] = &[
+    "text",
+    "json",
+    "rust",
+    "c",
+    "python",
+    "js",
+    "escpos",
+    "brf",
+    "kitty",
+    "iterm2",
+    "sixel",
+    "html",
+    "svg",
+    "png",
+    "asciicast",
+    "gif",
+];
+
+/// Name of the constant emitted by the source-code formats
+const SOURCE_CONST_NAME: &str = "TXTR_ART";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Rust,
+    C,
+    Python,
+    Js,
+    EscPos,
+    Brf,
+    Kitty,
+    Iterm2,
+    Sixel,
+    Html,
+    Svg,
+    Png,
+    Asciicast,
+    Gif,
+}
+
+pub fn select(name: &str) -> Format {
+    match name {
+        "text" => Format::Text,
+        "json" => Format::Json,
+        "rust" => Format::Rust,
+        "c" => Format::C,
+        "python" => Format::Python,
+        "js" => Format::Js,
+        "escpos" => Format::EscPos,
+        "brf" => Format::Brf,
+        "kitty" => Format::Kitty,
+        "iterm2" => Format::Iterm2,
+        "sixel" => Format::Sixel,
+        "html" => Format::Html,
+        "svg" => Format::Svg,
+        "png" => Format::Png,
+        "asciicast" => Format::Asciicast,
+        "gif" => Format::Gif,
+        _ => {
+            eprintln!(
+                "Warning: unknown format '{}', using 'text'. Valid formats: {}",
+                name,
+                VALID_FORMATS.join(", ")
+            );
+            Format::Text
+        }
+    }
+}
+
+/// Quote and escape a string for inclusion in JSON output
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_color(color: Option<Rgb>) -> String {
+    match color {
+        Some((r, g, b)) => format!("[{},{},{}]", r, g, b),
+        None => "null".to_string(),
+    }
+}
+
+/// Serialize a rendered grid as JSON: dimensions, ramp, level range and row-major cells
+pub fn to_json(grid: &Grid, ramp: &str, min: usize, max: usize) -> String {
+    let mut output = String::with_capacity(grid.cells.len() * 60 + 128);
+    output.push_str(&format!(
+        "{{\"width\":{},\"height\":{},\"ramp\":{},\"min\":{},\"max\":{},\"cells\":[",
+        grid.width,
+        grid.height,
+        json_string(ramp),
+        min,
+        max
+    ));
+    for (i, cell) in grid.cells.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        output.push_str(&format!(
+            "\n{{\"glyph\":{},\"level\":{},\"fg\":{},\"bg\":{}}}",
+            json_string(&cell.glyph.to_string()),
+            cell.level,
+            json_color(cell.fg),
+            json_color(cell.bg)
+        ));
+    }
+    output.push_str("\n]}\n");
+    output
+}
+
+/// Split a row into runs of cells sharing the same colors
+fn color_runs(row: &[Cell]) -> impl Iterator<Item = &[Cell]> {
+    row.chunk_by(|a, b| a.fg == b.fg && a.bg == b.bg)
+}
+
+fn hex_color((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Escape text for HTML and SVG content
+fn escape_xml(cells: &[Cell]) -> String {
+    let mut out = String::with_capacity(cells.len());
+    for cell in cells {
+        match cell.glyph {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render the grid as a standalone HTML page with one colored span per run
+pub fn to_html(grid: &Grid) -> String {
+    let mut output = String::with_capacity(grid.cells.len() * 8 + 512);
+    output.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    output.push_str("<title>txtr</title>\n</head>\n");
+    output.push_str(&format!(
+        "<body style=\"background:{bg}\">\n<pre style=\"font-family:monospace;line-height:1;color:{fg};background:{bg}\">",
+        fg = hex_color(DEFAULT_FG),
+        bg = hex_color(DEFAULT_BG)
+    ));
+    for row in grid.rows() {
+        for run in color_runs(row) {
+            let mut style = Vec::new();
+            if let Some(fg) = run[0].fg {
+                style.push(format!("color:{}", hex_color(fg)));
+            }
+            if let Some(bg) = run[0].bg {
+                style.push(format!("background:{}", hex_color(bg)));
+            }
+            if style.is_empty() {
+                output.push_str(&escape_xml(run));
+            } else {
+                output.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    style.join(";"),
+                    escape_xml(run)
+                ));
+            }
+        }
+        output.push('\n');
+    }
+    output.push_str("</pre>\n</body>\n</html>\n");
+    output
+}
+
+/// SVG rows are taller than the bitmap font so monospace fonts fit comfortably
+const SVG_CELL_HEIGHT: u32 = 16;
+
+/// Render the grid as SVG: a rect per background run and a text element per glyph run
+pub fn to_svg(grid: &Grid) -> String {
+    let (width, height) = (
+        grid.width as u32 * GLYPH_WIDTH,
+        grid.height as u32 * SVG_CELL_HEIGHT,
+    );
+    let mut output = String::with_capacity(grid.cells.len() * 16 + 512);
+    output.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"{size}\">\n",
+        w = width,
+        h = height,
+        size = GLYPH_HEIGHT
+    ));
+    output.push_str(&format!(
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        hex_color(DEFAULT_BG)
+    ));
+
+    for (y, row) in grid.rows().enumerate() {
+        let top = y as u32 * SVG_CELL_HEIGHT;
+        let mut x = 0;
+        for run in color_runs(row) {
+            let left = x as u32 * GLYPH_WIDTH;
+            if let Some(bg) = run[0].bg {
+                output.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    left,
+                    top,
+                    run.len() as u32 * GLYPH_WIDTH,
+                    SVG_CELL_HEIGHT,
+                    hex_color(bg)
+                ));
+            }
+            if run.iter().any(|cell| cell.glyph != ' ') {
+                // Baseline sits a quarter cell above the bottom for descenders
+                output.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\" fill=\"{}\" textLength=\"{}\" xml:space=\"preserve\">{}</text>\n",
+                    left,
+                    top + SVG_CELL_HEIGHT * 3 / 4,
+                    hex_color(run[0].fg.unwrap_or(DEFAULT_FG)),
+                    run.len() as u32 * GLYPH_WIDTH,
+                    escape_xml(run)
+                ));
+            }
+            x += run.len();
+        }
+    }
+    output.push_str("</svg>\n");
+    output
+}
+
+/// Record rendered frames as an asciicast v2 stream: a JSON header line, then one
+/// `[seconds, "o", text]` output event per frame, each drawn from the top-left
+/// corner. `frames` pairs each frame's text with how long it stays on screen.
+pub fn to_asciicast(frames: &[(String, u32)], width: usize, height: usize) -> String {
+    let mut output = format!(
+        "{{\"version\":2,\"width\":{},\"height\":{}}}\n",
+        width, height
+    );
+    let mut time_ms: u64 = 0;
+    for (i, (text, delay_ms)) in frames.iter().enumerate() {
+        // Clear once and hide the cursor; later frames simply overdraw
+        let mut data = String::from(if i == 0 {
+            "\x1b[?25l\x1b[2J\x1b[H"
+        } else {
+            "\x1b[H"
+        });
+        // The terminal is exactly as tall as the art, so a final newline would scroll
+        data.push_str(&text.trim_end_matches('\n').replace('\n', "\r\n"));
+        output.push_str(&format!(
+            "[{:.3}, \"o\", {}]\n",
+            time_ms as f64 / 1000.0,
+            json_string(&data)
+        ));
+        time_ms += *delay_ms as u64;
+    }
+    // Hold the last frame for its delay, then restore the cursor
+    output.push_str(&format!(
+        "[{:.3}, \"o\", {}]\n",
+        time_ms as f64 / 1000.0,
+        json_string("\x1b[?25h")
+    ));
+    output
+}
+
+/// Prefix every line of rendered text, e.g. to paste art into source comments
+pub fn prefix_lines(text: &str, prefix: &str) -> String {
+    let mut output = String::with_capacity(text.len() + text.lines().count() * prefix.len());
+    for line in text.lines() {
+        output.push_str(prefix);
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Escape one line of text as the body of a double-quoted string literal.
+/// ANSI escapes come out as `\x1b`; C also gets non-ASCII bytes as octal escapes.
+fn escape_literal(line: &str, lang: Format) -> String {
+    let mut out = String::with_capacity(line.len() + 16);
+    let mut hex_pending = false;
+    for c in line.chars() {
+        // A C hex escape swallows any following hex digits, so split the literal;
+        // Rust, Python and JS hex escapes are always two digits
+        if hex_pending && c.is_ascii_hexdigit() {
+            out.push_str("\"\"");
+        }
+        hex_pending = false;
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                out.push_str(&format!("\\x{:02x}", c as u32));
+                hex_pending = lang == Format::C;
+            }
+            c if !c.is_ascii() && lang == Format::C => {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    out.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Wrap rendered text in a constant declaration for the given language
+pub fn to_source(text: &str, lang: Format) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+
+    // Plain art can go into a Rust raw string verbatim; colored art needs escapes
+    if lang == Format::Rust && !text.chars().any(|c| c.is_control() && c != '\n') {
+        let mut hashes = String::from("#");
+        while text.contains(&format!("\"{}", hashes)) {
+            hashes.push('#');
+        }
+        return format!(
+            "pub const {}: &str = r{}\"{}\"{};\n",
+            SOURCE_CONST_NAME, hashes, text, hashes
+        );
+    }
+
+    let (open, separator, close) = match lang {
+        Format::Rust => (
+            format!("pub const {}: &str = concat!(", SOURCE_CONST_NAME),
+            ",",
+            ");",
+        ),
+        Format::C => (
+            format!("static const char {}[] =", SOURCE_CONST_NAME),
+            "",
+            ";",
+        ),
+        Format::Python => (format!("{} = (", SOURCE_CONST_NAME), "", ")"),
+        _ => (format!("export const {} =", SOURCE_CONST_NAME), " +", ";"),
+    };
+
+    let mut output = String::with_capacity(text.len() * 2 + 64);
+    output.push_str(&open);
+    output.push('\n');
+    for (i, line) in lines.iter().enumerate() {
+        output.push_str("    \"");
+        output.push_str(&escape_literal(line, lang));
+        output.push('"');
+        // Rust allows a trailing comma in concat!, JS does not allow a trailing +
+        if lang == Format::Rust || i + 1 < lines.len() {
+            output.push_str(separator);
+        }
+        output.push('\n');
+    }
+    if lines.is_empty() {
+        output.push_str("    \"\"\n");
+    }
+    output.push_str(close);
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_valid_formats() {
+        assert_eq!(select("text"), Format::Text);
+        assert_eq!(select("json"), Format::Json);
+    }
+
+    #[test]
+    fn select_source_formats() {
+        assert_eq!(select("rust"), Format::Rust);
+        assert_eq!(select("c"), Format::C);
+        assert_eq!(select("python"), Format::Python);
+        assert_eq!(select("js"), Format::Js);
+    }
+
+    #[test]
+    fn select_printer_format() {
+        assert_eq!(select("escpos"), Format::EscPos);
+        assert_eq!(select("brf"), Format::Brf);
+    }
+
+    #[test]
+    fn select_image_protocol_formats() {
+        assert_eq!(select("kitty"), Format::Kitty);
+        assert_eq!(select("iterm2"), Format::Iterm2);
+        assert_eq!(select("sixel"), Format::Sixel);
+    }
+
+    #[test]
+    fn select_document_formats() {
+        assert_eq!(select("html"), Format::Html);
+        assert_eq!(select("svg"), Format::Svg);
+        assert_eq!(select("png"), Format::Png);
+        assert_eq!(select("asciicast"), Format::Asciicast);
+        assert_eq!(select("gif"), Format::Gif);
+    }
+
+    #[test]
+    fn select_unknown_format_falls_back() {
+        assert_eq!(select("yaml"), Format::Text);
+        assert_eq!(select("JSON"), Format::Text); // case sensitive
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(json_string("\x1b[0m"), "\"\\u001b[0m\"");
+        assert_eq!(json_string("░"), "\"░\"");
+    }
+
+    #[test]
+    fn to_json_structure() {
+        let mut grid = Grid::new(2, 1, true);
+        grid.push(Cell {
+            glyph: '#',
+            level: 200,
+            fg: Some((1, 2, 3)),
+            ..Cell::blank()
+        });
+        grid.push(Cell::blank());
+        let json = to_json(&grid, " #", 0, 200);
+        assert!(json.starts_with(
+            "{\"width\":2,\"height\":1,\"ramp\":\" #\",\"min\":0,\"max\":200,\"cells\":["
+        ));
+        assert!(json.contains("{\"glyph\":\"#\",\"level\":200,\"fg\":[1,2,3],\"bg\":null}"));
+        assert!(json.contains("{\"glyph\":\" \",\"level\":0,\"fg\":null,\"bg\":null}"));
+        assert!(json.trim_end().ends_with("]}"));
+    }
+
+    fn colored_grid() -> Grid {
+        let mut grid = Grid::new(3, 1, true);
+        for (glyph, fg) in [
+            ('<', Some((255, 0, 0))),
+            ('a', Some((255, 0, 0))),
+            ('b', None),
+        ] {
+            grid.push(Cell {
+                glyph,
+                fg,
+                bg: Some((0, 0, 255)),
+                ..Cell::blank()
+            });
+        }
+        grid
+    }
+
+    #[test]
+    fn to_html_spans_and_escaping() {
+        let html = to_html(&colored_grid());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span style=\"color:#ff0000;background:#0000ff\">&lt;a</span>"));
+        assert!(html.contains("<span style=\"background:#0000ff\">b</span>\n</pre>"));
+    }
+
+    #[test]
+    fn to_svg_rects_and_text() {
+        let svg = to_svg(&colored_grid());
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"16\"")
+        );
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"16\" height=\"16\" fill=\"#0000ff\"/>"));
+        assert!(
+            svg.contains("fill=\"#ff0000\" textLength=\"16\" xml:space=\"preserve\">&lt;a</text>")
+        );
+        assert!(svg.contains("x=\"16\" y=\"12\" fill=\"#e5e5e5\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn to_asciicast_header_and_events() {
+        let frames = vec![("ab\ncd\n".to_string(), 100), ("ef\ngh\n".to_string(), 250)];
+        let cast = to_asciicast(&frames, 2, 2);
+        let lines: Vec<&str> = cast.lines().collect();
+        assert_eq!(lines[0], "{\"version\":2,\"width\":2,\"height\":2}");
+        assert_eq!(
+            lines[1],
+            "[0.000, \"o\", \"\\u001b[?25l\\u001b[2J\\u001b[Hab\\r\\ncd\"]"
+        );
+        assert_eq!(lines[2], "[0.100, \"o\", \"\\u001b[Hef\\r\\ngh\"]");
+        assert_eq!(lines[3], "[0.350, \"o\", \"\\u001b[?25h\"]");
+    }
+
+    #[test]
+    fn prefix_lines_prefixes_each_line() {
+        assert_eq!(prefix_lines("ab\ncd\n", "# "), "# ab\n# cd\n");
+    }
+
+    #[test]
+    fn to_source_rust_raw_string() {
+        let src = to_source("a\"#b\n", Format::Rust);
+        assert_eq!(src, "pub const TXTR_ART: &str = r##\"a\"#b\n\"##;\n");
+    }
+
+    #[test]
+    fn to_source_rust_escapes_ansi() {
+        let src = to_source("\x1b[0mx\n", Format::Rust);
+        assert_eq!(
+            src,
+            "pub const TXTR_ART: &str = concat!(\n    \"\\x1b[0mx\\n\",\n);\n"
+        );
+    }
+
+    #[test]
+    fn to_source_c_escapes() {
+        let src = to_source("\x1bA\"░\n", Format::C);
+        // The hex escape is split from the following hex digit
+        assert!(src.contains("\"\\x1b\"\"A\\\"\\342\\226\\221\\n\""));
+        assert!(src.starts_with("static const char TXTR_ART[] =\n"));
+        assert!(src.ends_with(";\n"));
+    }
+
+    #[test]
+    fn to_source_only_splits_c_hex_escapes() {
+        let rust = to_source("\x01A\n", Format::Rust);
+        assert!(rust.contains("    \"\\x01A\\n\",\n"));
+        let js = to_source("\x01A\n", Format::Js);
+        assert!(js.contains("    \"\\x01A\\n\"\n"));
+        let c = to_source("\x01A\n", Format::C);
+        assert!(c.contains("    \"\\x01\"\"A\\n\"\n"));
+    }
+
+    #[test]
+    fn to_source_js_concatenation() {
+        let src = to_source("a\nb\n", Format::Js);
+        assert_eq!(
+            src,
+            "export const TXTR_ART =\n    \"a\\n\" +\n    \"b\\n\"\n;\n"
+        );
+    }
+
+    #[test]
+    fn to_source_python_keeps_unicode() {
+        let src = to_source("░\n", Format::Python);
+        assert_eq!(src, "TXTR_ART = (\n    \"░\\n\"\n)\n");
+    }
+}
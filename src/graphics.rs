@@ -0,0 +1,380 @@
+use crate::format::Format;
+use crate::grid::Rgb;
+use crate::quantize;
+use image::{DynamicImage, ImageError, ImageFormat, RgbImage};
+use std::collections::BTreeSet;
+use std::io::Cursor;
+
+/// Pixels per terminal column when sending real images; the terminal scales
+/// the image to --width columns, this only sets how much detail is sent
+pub const CELL_WIDTH_PX: u32 = 10;
+
+/// Widest image sent with --format kitty or iterm2, however wide --width is
+pub const MAX_IMAGE_WIDTH_PX: u32 = 2048;
+
+/// Kitty requires payloads to be split into chunks of at most 4096 bytes
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Larger iTerm2 payloads are sent as a multipart file
+const ITERM2_CHUNK_SIZE: usize = 1 << 20;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, ImageError> {
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Encode frames as a looping animated GIF. All frames share one global palette
+/// so colors don't shift from frame to frame; `frames` pairs each image with its
+/// delay in milliseconds.
+pub fn gif(frames: &[(RgbImage, u32)]) -> Result<Vec<u8>, gif::EncodingError> {
+    let (width, height) = frames
+        .first()
+        .map(|(img, _)| img.dimensions())
+        .unwrap_or((1, 1));
+    let too_large = || gif::EncodingError::from(std::io::Error::other("image too large for GIF"));
+    let width = u16::try_from(width).map_err(|_| too_large())?;
+    let height = u16::try_from(height).map_err(|_| too_large())?;
+
+    // Text art has few distinct colors, so build the palette from unique colors
+    // only, in a fixed order so the same frames always encode the same bytes
+    let colors: BTreeSet<Rgb> = frames
+        .iter()
+        .flat_map(|(img, _)| img.pixels().map(|p| (p[0], p[1], p[2])))
+        .collect();
+    let palette = quantize::median_cut(&colors.into_iter().collect::<Vec<_>>(), 256);
+    let flat: Vec<u8> = palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+
+    let mut output = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut output, width, height, &flat)?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    for (img, delay_ms) in frames {
+        let pixels: Vec<Rgb> = img.pixels().map(|p| (p[0], p[1], p[2])).collect();
+        let indices =
+            quantize::map_to_palette(&pixels, width as usize, height as usize, &palette, false);
+        let indices: Vec<u8> = indices.into_iter().map(|i| i as u8).collect();
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, indices, None);
+        // GIF delays are in hundredths of a second
+        frame.delay = ((delay_ms + 5) / 10).min(u16::MAX as u32) as u16;
+        encoder.write_frame(&frame)?;
+    }
+    drop(encoder);
+    Ok(output)
+}
+
+/// Guess from the environment whether the terminal speaks the given image protocol
+pub fn supported(format: Format) -> bool {
+    detect(format, |name| std::env::var(name).ok())
+}
+
+fn detect(format: Format, env: impl Fn(&str) -> Option<String>) -> bool {
+    let term = env("TERM").unwrap_or_default();
+    let program = env("TERM_PROGRAM").unwrap_or_default();
+    match format {
+        Format::Kitty => {
+            term.contains("kitty")
+                || env("KITTY_WINDOW_ID").is_some()
+                || matches!(program.as_str(), "WezTerm" | "ghostty")
+        }
+        Format::Iterm2 => {
+            matches!(program.as_str(), "iTerm.app" | "WezTerm")
+                || env("LC_TERMINAL").as_deref() == Some("iTerm2")
+        }
+        _ => false,
+    }
+}
+
+/// Display an image with the kitty graphics protocol, scaled to `cols` cells
+pub fn kitty(img: &DynamicImage, cols: u32) -> Result<String, ImageError> {
+    let payload = base64(&encode_png(img)?);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut output = String::with_capacity(payload.len() + chunks.len() * 16 + 32);
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        output.push_str("\x1b_G");
+        if i == 0 {
+            // Transmit and display PNG data, quietly, scaled to the column count
+            output.push_str(&format!("a=T,f=100,q=2,c={},", cols));
+        }
+        output.push_str(&format!("m={};", more));
+        // Base64 is ASCII, so byte chunks are always valid UTF-8
+        output.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        output.push_str("\x1b\\");
+    }
+    output.push('\n');
+    Ok(output)
+}
+
+/// Display an image with the iTerm2 inline image protocol, scaled to `cols` cells
+pub fn iterm2(img: &DynamicImage, cols: u32) -> Result<String, ImageError> {
+    let png = encode_png(img)?;
+    let payload = base64(&png);
+    let args = format!(
+        "inline=1;size={};width={};preserveAspectRatio=1",
+        png.len(),
+        cols
+    );
+    let mut output = String::with_capacity(payload.len() + 128);
+
+    if payload.len() <= ITERM2_CHUNK_SIZE {
+        output.push_str(&format!("\x1b]1337;File={}:{}\x07", args, payload));
+    } else {
+        output.push_str(&format!("\x1b]1337;MultipartFile={}\x07", args));
+        for chunk in payload.as_bytes().chunks(ITERM2_CHUNK_SIZE) {
+            output.push_str("\x1b]1337;FilePart=");
+            output.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+            output.push('\x07');
+        }
+        output.push_str("\x1b]1337;FileEnd\x07");
+    }
+    output.push('\n');
+    Ok(output)
+}
+
+/// Append one row of sixel characters, run-length compressing repeats
+fn push_sixel_run(output: &mut String, c: char, count: usize) {
+    if count > 3 {
+        output.push_str(&format!("!{}{}", count, c));
+    } else {
+        output.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+/// Encode palette-indexed pixels as a Sixel image: palette definitions, then
+/// 6-pixel-tall bands with one run-length compressed pass per color. Pixels
+/// without an index are left undrawn, showing the terminal background.
+pub fn sixel(indices: &[Option<usize>], width: usize, height: usize, palette: &[Rgb]) -> String {
+    let mut output = String::with_capacity(width * height / 2 + palette.len() * 20 + 32);
+    // DCS q keeping undrawn pixels as they are (P2 = 1), then raster
+    // attributes: 1:1 pixel aspect ratio and image size
+    output.push_str(&format!("\x1bP0;1q\"1;1;{};{}", width, height));
+    for (i, &(r, g, b)) in palette.iter().enumerate() {
+        // Sixel colors are RGB percentages
+        let pct = |v: u8| (v as u32 * 100 + 127) / 255;
+        output.push_str(&format!("#{};2;{};{};{}", i, pct(r), pct(g), pct(b)));
+    }
+
+    // Undrawn rows at the bottom need no bands; the raster attributes keep the size
+    let drawn_height = indices
+        .chunks(width.max(1))
+        .rposition(|row| row.iter().any(Option::is_some))
+        .map_or(0, |y| y + 1);
+    for band in (0..drawn_height).step_by(6) {
+        if band > 0 {
+            output.push('-'); // next band
+        }
+        let rows = 6.min(height - band);
+        let mut used = vec![false; palette.len()];
+        for &index in indices[band * width..(band + rows) * width]
+            .iter()
+            .flatten()
+        {
+            used[index] = true;
+        }
+
+        let mut first = true;
+        for color in (0..palette.len()).filter(|&c| used[c]) {
+            if !first {
+                output.push('$'); // carriage return: overlay the next color on this band
+            }
+            first = false;
+            output.push_str(&format!("#{}", color));
+
+            let mut run_char = '?';
+            let mut run_len = 0;
+            for x in 0..width {
+                let mut bits = 0u8;
+                for dy in 0..rows {
+                    if indices[(band + dy) * width + x] == Some(color) {
+                        bits |= 1 << dy;
+                    }
+                }
+                let c = (63 + bits) as char;
+                if c == run_char {
+                    run_len += 1;
+                } else {
+                    push_sixel_run(&mut output, run_char, run_len);
+                    run_char = c;
+                    run_len = 1;
+                }
+            }
+            // Trailing empty sixels can be left out
+            if run_char != '?' {
+                push_sixel_run(&mut output, run_char, run_len);
+            }
+        }
+    }
+
+    output.push_str("\x1b\\\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn env_from<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn detect_kitty() {
+        assert!(detect(Format::Kitty, env_from(&[("TERM", "xterm-kitty")])));
+        assert!(detect(Format::Kitty, env_from(&[("KITTY_WINDOW_ID", "1")])));
+        assert!(!detect(
+            Format::Kitty,
+            env_from(&[("TERM", "xterm-256color")])
+        ));
+    }
+
+    #[test]
+    fn detect_iterm2() {
+        assert!(detect(
+            Format::Iterm2,
+            env_from(&[("TERM_PROGRAM", "iTerm.app")])
+        ));
+        assert!(detect(
+            Format::Iterm2,
+            env_from(&[("LC_TERMINAL", "iTerm2")])
+        ));
+        assert!(!detect(
+            Format::Iterm2,
+            env_from(&[("TERM", "xterm-kitty")])
+        ));
+    }
+
+    #[test]
+    fn kitty_chunks_large_payloads() {
+        // Noisy pixels defeat PNG compression so the payload spans several chunks
+        let img = RgbaImage::from_fn(64, 64, |x, y| {
+            let v = (x * 31 + y * 17) as u8 ^ (x * y) as u8;
+            Rgba([v, v.wrapping_mul(3), v.wrapping_mul(7), 255])
+        });
+        let output = kitty(&DynamicImage::ImageRgba8(img), 20).unwrap();
+        assert!(output.starts_with("\x1b_Ga=T,f=100,q=2,c=20,m=1;"));
+        assert!(output.matches("\x1b_G").count() > 1);
+        assert!(output.contains("\x1b_Gm=0;"));
+        for chunk in output.split("\x1b_G").skip(1) {
+            let (_, data) = chunk.split_once(';').unwrap();
+            let data = data.trim_end_matches('\n').trim_end_matches("\x1b\\");
+            assert!(data.len() <= KITTY_CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn iterm2_inline_image() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        let output = iterm2(&img, 40).unwrap();
+        assert!(output.starts_with("\x1b]1337;File=inline=1;size="));
+        assert!(output.contains(";width=40;preserveAspectRatio=1:iVBORw0KGgo"));
+        assert!(output.ends_with("\x07\n"));
+    }
+
+    #[test]
+    fn gif_shares_one_palette_across_frames() {
+        let red = RgbImage::from_pixel(4, 2, image::Rgb([255, 0, 0]));
+        let blue = RgbImage::from_pixel(4, 2, image::Rgb([0, 0, 255]));
+        let output = gif(&[(red, 100), (blue, 250)]).unwrap();
+        assert!(output.starts_with(b"GIF89a"));
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(output.as_slice())
+            .unwrap();
+        assert_eq!(decoder.global_palette().map(|p| p.len()), Some(6));
+        let first = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(first.delay, 10);
+        assert!(first.palette.is_none());
+        let second = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(second.delay, 25);
+        assert!(decoder.read_next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn gif_output_is_repeatable() {
+        // More colors than fit in the palette, with many ties in each channel
+        let img = RgbImage::from_fn(40, 40, |x, y| {
+            image::Rgb([(x * 6) as u8, (y * 6) as u8, ((x * y) % 7 * 36) as u8])
+        });
+        let frames = [(img, 100)];
+        assert_eq!(gif(&frames).unwrap(), gif(&frames).unwrap());
+    }
+
+    #[test]
+    fn sixel_run_length_encoding() {
+        let mut output = String::new();
+        push_sixel_run(&mut output, '~', 3);
+        push_sixel_run(&mut output, '@', 12);
+        assert_eq!(output, "~~~!12@");
+    }
+
+    #[test]
+    fn sixel_golden_output() {
+        // 5x2: left 4 pixels red, last column blue
+        let indices = [0, 0, 0, 0, 1, 0, 0, 0, 0, 1].map(Some);
+        let palette = [(255, 0, 0), (0, 0, 255)];
+        let output = sixel(&indices, 5, 2, &palette);
+        assert_eq!(
+            output,
+            "\x1bP0;1q\"1;1;5;2#0;2;100;0;0#1;2;0;0;100#0!4B$#1!4?B\x1b\\\n"
+        );
+    }
+
+    #[test]
+    fn sixel_bands_of_six_rows() {
+        let indices = vec![Some(0); 3 * 13];
+        let output = sixel(&indices, 3, 13, &[(0, 0, 0)]);
+        // 13 rows need 3 bands, with a separator between each
+        assert_eq!(output.matches('-').count(), 2);
+        assert!(output.ends_with("#0~~~-#0~~~-#0@@@\x1b\\\n"));
+    }
+
+    #[test]
+    fn sixel_leaves_transparent_pixels_undrawn() {
+        // 2x1: one red pixel, one transparent
+        let output = sixel(&[Some(0), None], 2, 1, &[(255, 0, 0)]);
+        assert!(output.ends_with("#0@\x1b\\\n"));
+        // Transparent bands at the bottom are left out
+        let mut indices = vec![None; 2 * 8];
+        indices[0] = Some(0);
+        let output = sixel(&indices, 2, 8, &[(255, 0, 0)]);
+        assert!(output.ends_with("#0;2;100;0;0#0@\x1b\\\n"));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::ansi::{self, ColorDepth};
+
+/// RGB color triple used throughout the cell grid
+pub type Rgb = (u8, u8, u8);
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DEFAULT_FG: &str = "\x1b[39m";
+const ANSI_DEFAULT_BG: &str = "\x1b[49m";
+const ANSI_NORMAL: &str = "\x1b[22;27m";
+
+/// How much of its color dim text keeps
+pub const DIM_FACTOR: f64 = 0.5;
+
+pub fn format_color(r: u8, g: u8, b: u8) -> String {
+    format!("\x1b[38;2;{};{};{}m", r, g, b)
+}
+
+pub fn format_bg_color(r: u8, g: u8, b: u8) -> String {
+    format!("\x1b[48;2;{};{};{}m", r, g, b)
+}
+
+/// SGR text attribute of a cell
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Attr {
+    #[default]
+    Normal,
+    Dim,
+    Bold,
+    Reverse,
+}
+
+impl Attr {
+    fn sgr(self) -> &'static str {
+        match self {
+            Attr::Normal => ANSI_NORMAL,
+            Attr::Dim => "\x1b[2m",
+            Attr::Bold => "\x1b[1m",
+            Attr::Reverse => "\x1b[7m",
+        }
+    }
+}
+
+/// A single rendered character cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub level: usize,
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+    pub attr: Attr,
+}
+
+impl Cell {
+    pub fn blank() -> Cell {
+        Cell {
+            glyph: ' ',
+            level: 0,
+            fg: None,
+            bg: None,
+            attr: Attr::Normal,
+        }
+    }
+}
+
+/// Row-major grid of rendered cells, independent of the output format
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Cell>,
+    pub color: bool,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, color: bool) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: Vec::with_capacity(width * height),
+            color,
+        }
+    }
+
+    pub fn push(&mut self, cell: Cell) {
+        self.cells.push(cell);
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        // max(1) keeps chunks() happy for empty grids
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Render the grid as terminal text, with 24-bit ANSI colors when enabled
+    pub fn to_ansi(&self) -> String {
+        self.to_ansi_with(ColorDepth::TrueColor)
+    }
+
+    /// Render the grid as terminal text using color codes of the given depth
+    pub fn to_ansi_with(&self, depth: ColorDepth) -> String {
+        // Pre-allocate output buffer: ~25 bytes per cell with color, ~2 without
+        let bytes_per_cell = if self.color { 25 } else { 2 };
+        let mut output = String::with_capacity(self.width * self.height * bytes_per_cell);
+
+        for row in self.rows() {
+            let mut fg_set = false;
+            let mut bg_set = false;
+            let mut attr = Attr::Normal;
+            for cell in row {
+                if cell.attr != attr {
+                    if attr != Attr::Normal {
+                        output.push_str(ANSI_NORMAL);
+                    }
+                    if cell.attr != Attr::Normal {
+                        output.push_str(cell.attr.sgr());
+                    }
+                    attr = cell.attr;
+                }
+                match cell.fg {
+                    Some(color) => {
+                        output.push_str(&ansi::fg_code(color, depth));
+                        fg_set = true;
+                    }
+                    // Spaces don't show the foreground, so only reset it for visible glyphs
+                    None if fg_set && (cell.glyph != ' ' || cell.attr == Attr::Reverse) => {
+                        output.push_str(ANSI_DEFAULT_FG);
+                        fg_set = false;
+                    }
+                    None => {}
+                }
+                match cell.bg {
+                    Some(color) => {
+                        output.push_str(&ansi::bg_code(color, depth));
+                        bg_set = true;
+                    }
+                    None if bg_set => {
+                        output.push_str(ANSI_DEFAULT_BG);
+                        bg_set = false;
+                    }
+                    None => {}
+                }
+                output.push(cell.glyph);
+            }
+            if self.color || attr != Attr::Normal {
+                output.push_str(ANSI_RESET);
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Bake attributes into colors the way a terminal shows them, for formats
+    /// that have no dim, bold or reverse text
+    pub fn resolve_attributes(&self) -> Grid {
+        let mut grid = self.clone();
+        for cell in &mut grid.cells {
+            match cell.attr {
+                Attr::Normal => continue,
+                Attr::Dim => {
+                    let (r, g, b) = cell.fg.unwrap_or(ansi::DEFAULT_FG);
+                    let dim = |c: u8| (c as f64 * DIM_FACTOR).round() as u8;
+                    cell.fg = Some((dim(r), dim(g), dim(b)));
+                }
+                Attr::Bold => cell.fg = Some(cell.fg.unwrap_or(ansi::BOLD_FG)),
+                Attr::Reverse => {
+                    (cell.fg, cell.bg) = (
+                        Some(cell.bg.unwrap_or(ansi::DEFAULT_BG)),
+                        Some(cell.fg.unwrap_or(ansi::DEFAULT_FG)),
+                    );
+                }
+            }
+            cell.attr = Attr::Normal;
+            grid.color = true;
+        }
+        grid
+    }
+
+    /// Lines of plain text as a grid, padded with spaces to the longest line
+    pub fn from_lines(lines: &[String]) -> Grid {
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut grid = Grid::new(width, lines.len(), false);
+        for line in lines {
+            let glyphs = line.chars().chain(std::iter::repeat(' ')).take(width);
+            for glyph in glyphs {
+                grid.push(Cell {
+                    glyph,
+                    ..Cell::blank()
+                });
+            }
+        }
+        grid
+    }
+
+    /// A grid of blank cells
+    pub fn blank(width: usize, height: usize, color: bool) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::blank(); width * height],
+            color,
+        }
+    }
+
+    /// Copy another grid in with its top left corner at (x, y), clipped to
+    /// this grid, keeping color on if either grid uses it
+    pub fn draw(&mut self, other: &Grid, x: usize, y: usize) {
+        let rows = self.height.saturating_sub(y);
+        let len = other.width.min(self.width.saturating_sub(x));
+        for (row_y, row) in other.rows().take(rows).enumerate() {
+            let start = (y + row_y) * self.width + x;
+            self.cells[start..start + len].copy_from_slice(&row[..len]);
+        }
+        self.color |= other.color;
+    }
+
+    /// A blank grid of the given size with this one drawn at (x, y)
+    fn placed(&self, width: usize, height: usize, x: usize, y: usize) -> Grid {
+        let mut grid = Grid::blank(width, height, false);
+        grid.draw(self, x, y);
+        grid
+    }
+
+    /// This grid with another below it, the narrower one centered
+    pub fn above(&self, below: &Grid) -> Grid {
+        let width = self.width.max(below.width);
+        let height = self.height + below.height;
+        let mut grid = self.placed(width, height, (width - self.width) / 2, 0);
+        let lower = below.placed(width, height, (width - below.width) / 2, self.height);
+        grid.cells[self.height * width..].copy_from_slice(&lower.cells[self.height * width..]);
+        grid.color |= below.color;
+        grid
+    }
+
+    /// This grid with another to its right after `gap` blank columns, the
+    /// shorter one centered
+    pub fn beside(&self, right: &Grid, gap: usize) -> Grid {
+        let width = self.width + gap + right.width;
+        let height = self.height.max(right.height);
+        let mut grid = self.placed(width, height, 0, (height - self.height) / 2);
+        let right = right.placed(width, height, self.width + gap, (height - right.height) / 2);
+        for (row, other) in grid.cells.chunks_mut(width).zip(right.cells.chunks(width)) {
+            row[self.width + gap..].copy_from_slice(&other[self.width + gap..]);
+        }
+        grid.color |= right.color;
+        grid
+    }
+
+    /// Take the cells of another grid the same size wherever the row-major
+    /// mask is false, keeping color on if either grid uses it
+    pub fn composite(&mut self, outside: &Grid, inside: &[bool]) {
+        let cells = self.cells.iter_mut().zip(&outside.cells).zip(inside);
+        for ((cell, other), &inside) in cells {
+            if !inside {
+                *cell = *other;
+            }
+        }
+        self.color |= outside.color;
+    }
+
+    /// Snap every color to what the given depth can show
+    pub fn requantize(&mut self, depth: ColorDepth) {
+        for cell in &mut self.cells {
+            cell.fg = cell.fg.map(|c| ansi::requantize(c, depth));
+            cell.bg = cell.bg.map(|c| ansi::requantize(c, depth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(glyph: char, fg: Option<Rgb>, bg: Option<Rgb>) -> Cell {
+        Cell {
+            glyph,
+            level: 0,
+            fg,
+            bg,
+            attr: Attr::Normal,
+        }
+    }
+
+    #[test]
+    fn to_ansi_plain_text() {
+        let mut grid = Grid::new(2, 2, false);
+        for c in ['a', 'b', 'c', 'd'] {
+            grid.push(cell(c, None, None));
+        }
+        assert_eq!(grid.to_ansi(), "ab\ncd\n");
+    }
+
+    #[test]
+    fn to_ansi_colors_and_reset() {
+        let mut grid = Grid::new(1, 1, true);
+        grid.push(cell('x', Some((1, 2, 3)), Some((4, 5, 6))));
+        assert_eq!(grid.to_ansi(), "\x1b[38;2;1;2;3m\x1b[48;2;4;5;6mx\x1b[0m\n");
+    }
+
+    #[test]
+    fn to_ansi_restores_default_background() {
+        let mut grid = Grid::new(2, 1, true);
+        grid.push(cell('x', None, Some((4, 5, 6))));
+        grid.push(cell(' ', None, None));
+        assert!(grid.to_ansi().contains("\x1b[49m "));
+    }
+
+    #[test]
+    fn to_ansi_with_reduced_depth() {
+        let mut grid = Grid::new(1, 1, true);
+        grid.push(cell('x', Some((255, 0, 0)), Some((0, 0, 0))));
+        assert_eq!(
+            grid.to_ansi_with(ColorDepth::Ansi256),
+            "\x1b[38;5;196m\x1b[48;5;16mx\x1b[0m\n"
+        );
+        grid.requantize(ColorDepth::Ansi16);
+        assert_eq!(grid.cells[0].fg, Some((255, 0, 0)));
+    }
+
+    #[test]
+    fn to_ansi_attributes() {
+        let mut grid = Grid::new(4, 1, false);
+        for attr in [Attr::Bold, Attr::Bold, Attr::Normal, Attr::Reverse] {
+            grid.push(Cell {
+                attr,
+                ..cell('x', None, None)
+            });
+        }
+        assert_eq!(grid.to_ansi(), "\x1b[1mxx\x1b[22;27mx\x1b[7mx\x1b[0m\n");
+    }
+
+    #[test]
+    fn resolve_attributes_bakes_colors() {
+        let mut grid = Grid::new(3, 1, false);
+        for attr in [Attr::Dim, Attr::Bold, Attr::Reverse] {
+            grid.push(Cell {
+                attr,
+                ..cell('x', None, None)
+            });
+        }
+        let grid = grid.resolve_attributes();
+        assert!(grid.color);
+        assert_eq!(grid.cells[0].fg, Some((115, 115, 115)));
+        assert_eq!(grid.cells[1].fg, Some(ansi::BOLD_FG));
+        assert_eq!(grid.cells[2].fg, Some(ansi::DEFAULT_BG));
+        assert_eq!(grid.cells[2].bg, Some(ansi::DEFAULT_FG));
+        assert!(grid.cells.iter().all(|cell| cell.attr == Attr::Normal));
+    }
+
+    #[test]
+    fn above_and_beside_center_the_smaller_grid() {
+        let caption = Grid::from_lines(&["ab".to_string(), "c".to_string()]);
+        assert_eq!(caption.to_ansi(), "ab\nc \n");
+
+        let mut art = Grid::new(4, 1, true);
+        for _ in 0..4 {
+            art.push(cell('#', Some((9, 9, 9)), None));
+        }
+        let stacked = art.above(&caption);
+        assert_eq!((stacked.width, stacked.height), (4, 3));
+        assert!(stacked.color);
+        assert_eq!(stacked.cells[5].glyph, 'a');
+        assert_eq!(stacked.cells[9].glyph, 'c');
+
+        let side = caption.beside(&art, 1);
+        assert_eq!((side.width, side.height), (7, 2));
+        assert_eq!(side.cells[3], cell('#', Some((9, 9, 9)), None));
+        assert_eq!(side.cells[10].glyph, ' ');
+    }
+
+    #[test]
+    fn draw_clips_to_the_grid() {
+        let mut grid = Grid::blank(3, 2, false);
+        let art = Grid::from_lines(&["ab".to_string(), "cd".to_string()]);
+        grid.draw(&art, 2, 1);
+        assert_eq!(grid.to_ansi(), "   \n  a\n");
+    }
+
+    #[test]
+    fn composite_takes_outside_cells() {
+        let mut grid = Grid::new(3, 1, false);
+        let mut outside = Grid::new(3, 1, true);
+        for _ in 0..3 {
+            grid.push(cell('#', None, None));
+            outside.push(cell('.', Some((1, 2, 3)), None));
+        }
+        grid.composite(&outside, &[true, false, true]);
+        assert_eq!(grid.cells[0], cell('#', None, None));
+        assert_eq!(grid.cells[1], cell('.', Some((1, 2, 3)), None));
+        assert!(grid.color);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::edges;
+
+pub const VALID_LINE_STYLES: &[&str] = &["light", "heavy", "double"];
+
+/// Box-drawing weights for line art
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineStyle {
+    Light,
+    Heavy,
+    Double,
+}
+
+pub fn select(name: &str) -> LineStyle {
+    match name {
+        "light" => LineStyle::Light,
+        "heavy" => LineStyle::Heavy,
+        "double" => LineStyle::Double,
+        _ => {
+            eprintln!(
+                "Warning: unknown line style '{}', using 'light'. Valid line styles: {}",
+                name,
+                VALID_LINE_STYLES.join(", ")
+            );
+            LineStyle::Light
+        }
+    }
+}
+
+// Neighbor bits for the glyph tables
+const UP: usize = 1;
+const RIGHT: usize = 2;
+const DOWN: usize = 4;
+const LEFT: usize = 8;
+
+/// Box-drawing characters indexed by connected neighbors (UP | RIGHT | DOWN | LEFT).
+/// Line ends draw as straight lines, since double lines have no half-line glyphs.
+const LIGHT: [char; 16] = [
+    ' ', '│', '─', '└', '│', '│', '┌', '├', '─', '┘', '─', '┴', '┐', '┤', '┬', '┼',
+];
+const HEAVY: [char; 16] = [
+    ' ', '┃', '━', '┗', '┃', '┃', '┏', '┣', '━', '┛', '━', '┻', '┓', '┫', '┳', '╋',
+];
+const DOUBLE: [char; 16] = [
+    ' ', '║', '═', '╚', '║', '║', '╔', '╠', '═', '╝', '═', '╩', '╗', '╣', '╦', '╬',
+];
+
+impl LineStyle {
+    fn table(self) -> &'static [char; 16] {
+        match self {
+            LineStyle::Light => &LIGHT,
+            LineStyle::Heavy => &HEAVY,
+            LineStyle::Double => &DOUBLE,
+        }
+    }
+
+    /// Every character the style draws, for the JSON ramp
+    pub fn chars(self) -> String {
+        let mut chars: Vec<char> = self.table()[1..].to_vec();
+        chars.sort_unstable();
+        chars.dedup();
+        chars.into_iter().collect()
+    }
+}
+
+/// Whether (x, y) is inside the mask and set
+fn set(mask: &[bool], width: usize, height: usize, x: isize, y: isize) -> bool {
+    x >= 0
+        && y >= 0
+        && x < width as isize
+        && y < height as isize
+        && mask[y as usize * width + x as usize]
+}
+
+/// Set pixels between two set neighbors across from each other. A one-pixel
+/// line has edges on both sides but none down its middle, where the
+/// gradient cancels out; filling the gap lets thinning find the line itself.
+fn fill_gaps(mask: &mut [bool], width: usize, height: usize) {
+    let source = mask.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let across = (x > 0 && x + 1 < width && source[i - 1] && source[i + 1])
+                || (y > 0 && y + 1 < height && source[i - width] && source[i + width]);
+            mask[i] |= across;
+        }
+    }
+}
+
+/// Thin a row-major mask to one-pixel-wide lines with Zhang-Suen thinning,
+/// which peels boundary pixels from alternate sides until only the
+/// skeleton is left
+fn thin(mask: &mut [bool], width: usize, height: usize) {
+    let at = |mask: &[bool], x, y| set(mask, width, height, x, y);
+
+    loop {
+        let mut changed = false;
+        for step in 0..2 {
+            let mut remove = Vec::new();
+            for (i, _) in mask.iter().enumerate().filter(|(_, &set)| set) {
+                let (x, y) = ((i % width) as isize, (i / width) as isize);
+                // Neighbors clockwise from north
+                let p = [
+                    at(mask, x, y - 1),
+                    at(mask, x + 1, y - 1),
+                    at(mask, x + 1, y),
+                    at(mask, x + 1, y + 1),
+                    at(mask, x, y + 1),
+                    at(mask, x - 1, y + 1),
+                    at(mask, x - 1, y),
+                    at(mask, x - 1, y - 1),
+                ];
+                let neighbors = p.iter().filter(|&&set| set).count();
+                let transitions = (0..8).filter(|&k| !p[k] && p[(k + 1) % 8]).count();
+                let (n, e, s, w) = (p[0], p[2], p[4], p[6]);
+                // The first step peels south-east boundaries, the second north-west
+                let side = if step == 0 {
+                    !(e && s && (n || w))
+                } else {
+                    !(n && w && (e || s))
+                };
+                if (2..=6).contains(&neighbors) && transitions == 1 && side {
+                    remove.push(i);
+                }
+            }
+            changed |= !remove.is_empty();
+            for i in remove {
+                mask[i] = false;
+            }
+        }
+        if !changed {
+            return;
+        }
+    }
+}
+
+/// Thinning cuts corners where lines turn; move each cut corner out to where
+/// the lines would meet. A pixel linking to just two neighbors at right
+/// angles, which carry on away from each other, is replaced by the pixel
+/// across from it.
+fn square_corners(mask: &mut [bool], width: usize, height: usize) {
+    let at = |mask: &[bool], x, y| set(mask, width, height, x, y);
+    let directions = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+    for i in 0..mask.len() {
+        if !mask[i] {
+            continue;
+        }
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        let links: Vec<(isize, isize)> = directions
+            .into_iter()
+            .filter(|&(dx, dy)| at(mask, x + dx, y + dy))
+            .collect();
+        let [(ax, ay), (bx, by)] = links[..] else {
+            continue;
+        };
+        if ax + bx == 0 && ay + by == 0 {
+            continue; // a straight line
+        }
+        let (ox, oy) = (x + ax + bx, y + ay + by);
+        if at(mask, ox, oy)
+            || !at(mask, x + ax - bx, y + ay - by)
+            || !at(mask, x + bx - ax, y + by - ay)
+        {
+            continue;
+        }
+        mask[i] = false;
+        mask[oy as usize * width + ox as usize] = true;
+    }
+}
+
+/// Box-drawing glyphs for the edges of a row-major level grid: pixels with a
+/// gradient stronger than the threshold are thinned into a skeleton, diagonal
+/// steps are filled in so every line runs through side-by-side cells, and each
+/// pixel takes the glyph joining the neighbors it connects to. Isolated pixels
+/// and flat areas get None.
+pub fn glyphs(
+    levels: &[usize],
+    width: usize,
+    height: usize,
+    threshold: usize,
+    style: LineStyle,
+) -> Vec<Option<char>> {
+    let mut mask = edges::mask(levels, width, height, threshold);
+    fill_gaps(&mut mask, width, height);
+    thin(&mut mask, width, height);
+
+    // Turn diagonal steps into corners
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width {
+            if !mask[y * width + x] || mask[(y + 1) * width + x] {
+                continue;
+            }
+            let diagonal = [x.checked_sub(1), Some(x + 1).filter(|&x| x < width)]
+                .into_iter()
+                .flatten()
+                .any(|dx| mask[(y + 1) * width + dx] && !mask[y * width + dx]);
+            if diagonal {
+                mask[(y + 1) * width + x] = true;
+            }
+        }
+    }
+    square_corners(&mut mask, width, height);
+
+    let table = style.table();
+    (0..mask.len())
+        .map(|i| {
+            if !mask[i] {
+                return None;
+            }
+            let (x, y) = (i % width, i / width);
+            let mut links = 0;
+            if y > 0 && mask[i - width] {
+                links |= UP;
+            }
+            if x + 1 < width && mask[i + 1] {
+                links |= RIGHT;
+            }
+            if y + 1 < height && mask[i + width] {
+                links |= DOWN;
+            }
+            if x > 0 && mask[i - 1] {
+                links |= LEFT;
+            }
+            (links != 0).then(|| table[links])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(glyphs: &[Option<char>], width: usize) -> Vec<String> {
+        glyphs
+            .chunks(width)
+            .map(|row| row.iter().map(|g| g.unwrap_or(' ')).collect())
+            .collect()
+    }
+
+    /// Levels for a picture drawn with '#' as bright pixels
+    fn picture(rows: &[&str]) -> (Vec<usize>, usize, usize) {
+        let levels = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|c| if c == '#' { 255 } else { 0 }))
+            .collect();
+        (levels, rows[0].len(), rows.len())
+    }
+
+    #[test]
+    fn thin_keeps_the_middle_of_a_bar() {
+        let mut mask = vec![false; 7 * 5];
+        for y in 1..4 {
+            for x in 0..7 {
+                mask[y * 7 + x] = true;
+            }
+        }
+        thin(&mut mask, 7, 5);
+        // One unbroken line down the middle row
+        for (y, row) in mask.chunks(7).enumerate() {
+            let set: Vec<usize> = (0..7).filter(|&x| row[x]).collect();
+            if y == 2 {
+                assert!(!set.is_empty());
+                assert_eq!(set.len(), set[set.len() - 1] - set[0] + 1);
+            } else {
+                assert!(set.is_empty(), "row {}", y);
+            }
+        }
+    }
+
+    #[test]
+    fn rectangle_outline_has_corners() {
+        let (levels, width, height) = picture(&[
+            "................",
+            "................",
+            "..############..",
+            "..#..........#..",
+            "..#..........#..",
+            "..#..........#..",
+            "..#..........#..",
+            "..#..........#..",
+            "..############..",
+            "................",
+            "................",
+        ]);
+        let rows = render(&glyphs(&levels, width, height, 64, LineStyle::Light), width);
+        assert_eq!(rows[2], "  ┌──────────┐  ", "{:#?}", rows);
+        assert_eq!(rows[5], "  │          │  ", "{:#?}", rows);
+        assert_eq!(rows[8], "  └──────────┘  ", "{:#?}", rows);
+    }
+
+    #[test]
+    fn crossing_lines_join() {
+        let (levels, width, height) = picture(&[
+            ".............",
+            "......#......",
+            "......#......",
+            "......#......",
+            "......#......",
+            ".###########.",
+            "......#......",
+            "......#......",
+            "......#......",
+            "......#......",
+            ".............",
+        ]);
+        let rows = render(
+            &glyphs(&levels, width, height, 64, LineStyle::Double),
+            width,
+        );
+        assert_eq!(rows[5].chars().nth(6), Some('╬'), "{:#?}", rows);
+        assert_eq!(rows[3].chars().nth(6), Some('║'), "{:#?}", rows);
+        assert_eq!(rows[5].chars().nth(3), Some('═'), "{:#?}", rows);
+    }
+
+    #[test]
+    fn diagonals_become_steps() {
+        let (levels, width, height) = picture(&[
+            "..........",
+            "..........",
+            "..#.......",
+            "...#......",
+            "....#.....",
+            ".....#....",
+            "......#...",
+            "..........",
+            "..........",
+        ]);
+        let rows = render(&glyphs(&levels, width, height, 64, LineStyle::Light), width);
+        let art = rows.join("\n");
+        assert!(art.contains('┐') && art.contains('└'), "{:#?}", rows);
+        assert!(!art.contains('┼'), "{:#?}", rows);
+    }
+
+    #[test]
+    fn flat_image_has_no_lines() {
+        let levels = vec![90; 25];
+        assert!(glyphs(&levels, 5, 5, 64, LineStyle::Heavy)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn style_chars() {
+        assert_eq!(LineStyle::Light.chars().chars().count(), 11);
+        assert!(LineStyle::Heavy.chars().contains('╋'));
+    }
+}
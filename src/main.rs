@@ -1,8 +1,27 @@
+mod animation;
+mod ansi;
+mod braille;
 mod cli;
+mod cp437;
+mod dither;
+mod edges;
+mod emoji;
 mod encoder;
+mod escpos;
+mod figlet;
+mod font;
+mod format;
+mod graphics;
+mod grid;
+mod lines;
+mod mosaic;
+mod quantize;
+mod raster;
+mod sheet;
 mod txtr;
 
 use clap::Parser;
+use std::io::{Read, Write};
 
 // Compile-time check that cli.rs default matches txtr::DEFAULT_CHARS
 // (cli.rs uses literal "#$%{/;:,.. " which must match this constant)
@@ -21,6 +40,11 @@ const _: () = {
 fn main() {
     let args = cli::Args::parse();
 
+    if let Some(cli::Command::Convert(convert_args)) = &args.command {
+        convert(convert_args);
+        return;
+    }
+
     // Determine character set: use --chars if explicitly provided (non-default), otherwise use ramp preset
     let chars = if args.chars != txtr::DEFAULT_CHARS {
         args.chars.clone()
@@ -28,6 +52,159 @@ fn main() {
         txtr::get_ramp(&args.ramp).to_string()
     };
 
+    let output_format = format::select(&args.format);
+    if args.comment_prefix.is_some()
+        && matches!(
+            output_format,
+            format::Format::Json
+                | format::Format::EscPos
+                | format::Format::Html
+                | format::Format::Svg
+                | format::Format::Png
+                | format::Format::Asciicast
+                | format::Format::Gif
+        )
+    {
+        eprintln!(
+            "Warning: --comment-prefix is ignored with --format {}",
+            args.format
+        );
+    }
+    if args.raster && output_format != format::Format::EscPos {
+        eprintln!("Warning: --raster only applies to --format escpos");
+    }
+    if args.edge_color.is_some() && !args.edges && !args.edge_overlay && args.lines.is_none() {
+        eprintln!("Warning: --edge-color only applies to --edges, --edge-overlay and --lines");
+    }
+    let escpos_raster = args.raster && output_format == format::Format::EscPos;
+    let brf = output_format == format::Format::Brf;
+
+    // Real inline images need terminal support; otherwise fall back to half blocks
+    let image_protocol = matches!(
+        output_format,
+        format::Format::Kitty | format::Format::Iterm2
+    );
+    let inline_image = image_protocol && graphics::supported(output_format);
+    if image_protocol && !inline_image {
+        eprintln!(
+            "Warning: terminal does not appear to support --format {}, falling back to --blocks",
+            args.format
+        );
+    }
+    let blocks = args.blocks || (image_protocol && !inline_image);
+    let mosaic = args.mosaic.as_deref().map(mosaic::select);
+    let line_style = args.lines.as_deref().map(lines::select);
+    let separation = args.separate.as_deref().map(encoder::separation);
+    let background = txtr::select_background(&args.bg);
+    let shape = txtr::select_match(&args.match_mode) == txtr::Match::Shape;
+    let glyphs = if shape {
+        font::glyph_set(&args.glyphs)
+    } else {
+        Vec::new()
+    };
+    if shape && glyphs.is_empty() {
+        eprintln!(
+            "Error: --glyphs selected no glyphs (valid sets: {}).",
+            font::VALID_GLYPH_SETS.join(", ")
+        );
+        std::process::exit(1);
+    }
+    let sixel = output_format == format::Format::Sixel;
+    if separation.is_some() && (escpos_raster || brf || inline_image || sixel) {
+        eprintln!(
+            "Error: --format {} can't show --separate panels.",
+            args.format
+        );
+        std::process::exit(1);
+    }
+    if args.caption.is_some() && (escpos_raster || brf || inline_image || sixel) {
+        eprintln!(
+            "Warning: --caption is ignored with --format {}",
+            args.format
+        );
+    }
+    // A blank caption would only add blank rows
+    let caption = args
+        .caption
+        .as_deref()
+        .filter(|text| !text.trim().is_empty());
+    let caption = caption.map(|text| {
+        let font = match figlet::Font::load(&args.caption_font) {
+            Ok(font) => font,
+            Err(e) => {
+                eprintln!(
+                    "Error: {} (bundled fonts: {})",
+                    e,
+                    figlet::VALID_FONTS.join(", ")
+                );
+                std::process::exit(1);
+            }
+        };
+        // Each line of the caption is a row of big letters
+        let lines: Vec<grid::Grid> = text
+            .split('\n')
+            .map(|line| grid::Grid::from_lines(&font.render(line)))
+            .collect();
+        let first = lines[0].clone();
+        Caption {
+            grid: lines[1..].iter().fold(first, |grid, line| grid.above(line)),
+            position: figlet::select_position(&args.caption_position),
+            gradient: args.caption_gradient,
+        }
+    });
+
+    if args.emoji
+        && matches!(
+            output_format,
+            format::Format::EscPos
+                | format::Format::Svg
+                | format::Format::Png
+                | format::Format::Gif
+                | format::Format::Kitty
+                | format::Format::Iterm2
+                | format::Format::Sixel
+        )
+    {
+        eprintln!("Error: --format {} can't draw emoji.", args.format);
+        std::process::exit(1);
+    }
+
+    if sixel && !(2..=256).contains(&args.sixel_colors) {
+        eprintln!(
+            "Error: --sixel-colors must be between 2 and 256 (got {}).",
+            args.sixel_colors
+        );
+        std::process::exit(1);
+    }
+
+    let text = args.text_source.as_ref().map(|path| {
+        let text = match std::fs::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let text = txtr::flowing_text(&text, args.collapse_whitespace);
+        if text.iter().all(|&c| c == ' ') {
+            eprintln!("Error: --text-source {} has no text to print.", path);
+            std::process::exit(1);
+        }
+        text
+    });
+
+    let two_tone = args.two_tone.as_ref().map(|glyphs| {
+        let glyphs: Vec<char> = glyphs.chars().collect();
+        if glyphs.len() != 2 {
+            eprintln!(
+                "Error: --two-tone needs exactly 2 characters (got {}).",
+                glyphs.len()
+            );
+            std::process::exit(1);
+        }
+        (glyphs[0], glyphs[1])
+    });
+
     let char_count = chars.chars().count();
     if char_count < 2 {
         eprintln!("Error: Please use 2 or more characters (got {}).", char_count);
@@ -39,6 +216,11 @@ fn main() {
         std::process::exit(1);
     }
 
+    if args.printer_width < 1 {
+        eprintln!("Please increase printer width.");
+        std::process::exit(1);
+    }
+
     if args.fontsize < 0.01 {
         eprintln!("Please increase fontsize.");
         std::process::exit(1);
@@ -60,7 +242,32 @@ fn main() {
     }
 
     // Warn about incompatible flag combinations
-    if args.blocks {
+    if args.blocks
+        || args.braille
+        || mosaic.is_some()
+        || shape
+        || args.edges
+        || line_style.is_some()
+        || args.emoji
+        || two_tone.is_some()
+    {
+        let mode = if args.blocks {
+            "--blocks"
+        } else if args.braille {
+            "--braille"
+        } else if mosaic.is_some() {
+            "--mosaic"
+        } else if args.edges {
+            "--edges"
+        } else if line_style.is_some() {
+            "--lines"
+        } else if args.emoji {
+            "--emoji"
+        } else if two_tone.is_some() {
+            "--two-tone"
+        } else {
+            "--match shape"
+        };
         let mut ignored = Vec::new();
         if args.chars != txtr::DEFAULT_CHARS {
             ignored.push("--chars");
@@ -71,54 +278,369 @@ fn main() {
         if args.print_in_order {
             ignored.push("--print-in-order");
         }
-        if args.dither {
+        if args.dither && !args.braille && two_tone.is_none() {
             ignored.push("--dither");
         }
         if !ignored.is_empty() {
+            eprintln!("Warning: {} mode ignores: {}", mode, ignored.join(", "));
+        }
+    }
+
+    // --blocks, --mosaic and --bg imply color mode, and --emoji needs the colors
+    let color_enabled = args.color
+        || blocks
+        || mosaic.is_some()
+        || background != txtr::Background::None
+        || args.emoji;
+
+    let mask = args.mask.as_deref().map(|source| match source {
+        "alpha" => txtr::MaskSource::Alpha(args.alpha_threshold),
+        "level" => txtr::MaskSource::Level(args.level),
+        path => match image::open(path) {
+            Ok(img) => txtr::MaskSource::Image(img),
+            Err(e) => {
+                eprintln!("Error loading mask {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+    });
+    let outside = Outside {
+        chars: match &args.outside_chars {
+            Some(chars) if !chars.is_empty() => chars.clone(),
+            Some(_) => {
+                eprintln!("Error: --outside-chars needs at least 1 character.");
+                std::process::exit(1);
+            }
+            None if args.outside_ramp == "none" => " ".to_string(),
+            None => txtr::get_ramp(&args.outside_ramp).to_string(),
+        },
+        encoder: args
+            .outside_encoder
+            .clone()
+            .unwrap_or_else(|| args.encoder.clone()),
+        color: args.outside_color.unwrap_or(color_enabled),
+    };
+
+    let load = |path: &str| {
+        let art = match txtr::Txtr::new(
+            path,
+            encoder::select(&args.encoder),
+            red,
+            green,
+            blue,
+            color_enabled,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error loading image {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        // Validate image dimensions
+        if art.img.width() == 0 || art.img.height() == 0 {
+            eprintln!("Error: Image {} has zero width or height", path);
+            std::process::exit(1);
+        }
+        art
+    };
+
+    let render = Render {
+        chars,
+        blocks,
+        mosaic,
+        glyphs,
+        background,
+        text,
+        two_tone,
+        lines: line_style,
+        mask: mask.map(|source| (source, outside)),
+    };
+
+    // Sub-cell modes keep --width in columns. A cell is about twice as tall as
+    // it is wide, so rows scale by the sub-pixel count against that.
+    let sub_cell = if blocks {
+        None
+    } else if args.braille {
+        Some((2, 4))
+    } else if shape {
+        Some((font::GLYPH_WIDTH as usize, font::GLYPH_HEIGHT as usize))
+    } else {
+        mosaic.map(|m| m.cell_size())
+    };
+    let (cell_cols, row_scale) = match sub_cell {
+        Some((w, h)) => (w as u32, h as f32 / (2 * w) as f32),
+        None => (1, 1.0),
+    };
+
+    // Several files, or --grid, make a contact sheet of labeled thumbnails
+    if args.files.len() > 1 || args.grid.is_some() {
+        if separation.is_some() {
+            eprintln!("Error: --separate shows the channels of a single image.");
+            std::process::exit(1);
+        }
+        if escpos_raster || brf || inline_image || sixel {
+            eprintln!(
+                "Error: --format {} can't show a contact sheet.",
+                args.format
+            );
+            std::process::exit(1);
+        }
+        if args.emoji {
+            eprintln!("Error: --emoji can't be laid out in a contact sheet.");
+            std::process::exit(1);
+        }
+        let count = args.files.len();
+        let (cols, rows) = args.grid.map_or_else(
+            || sheet::default_layout(count),
+            |(c, r)| (c as usize, r as usize),
+        );
+        if count > cols * rows {
             eprintln!(
-                "Warning: --blocks mode ignores: {}",
-                ignored.join(", ")
+                "Warning: --grid {}x{} shows the first {} of {} images",
+                cols,
+                rows,
+                cols * rows,
+                count
             );
         }
+        let sheet_width = if output_format == format::Format::EscPos {
+            args.printer_width
+        } else {
+            args.width
+        };
+        let thumbnail_width =
+            sheet::thumbnail_width(sheet_width as usize, cols, args.gutter, args.border) as u32;
+        let (width, fontsize) = (thumbnail_width * cell_cols, args.fontsize * row_scale);
+
+        let mut tiles = Vec::new();
+        let (mut ramp, mut min, mut max) = (String::new(), usize::MAX, 0);
+        let mut first = None;
+        for path in args.files.iter().take(cols * rows) {
+            let mut art = load(path);
+            check_size(&art, width, fontsize);
+            prepare(&mut art, &args, width, fontsize);
+            let (grid, used) = render_grid(&mut art, &args, &render);
+            let label = sheet::label(path);
+            tiles.push(sheet::tile(
+                &grid,
+                &label,
+                thumbnail_width as usize,
+                args.border,
+            ));
+            (ramp, min, max) = (used, min.min(art.min), max.max(art.max));
+            first.get_or_insert(art);
+        }
+        let mut grid = sheet::layout(&tiles, cols, args.gutter);
+        // The caption goes with the whole sheet, colored from the first image
+        if let (Some(caption), Some(art)) = (&caption, &first) {
+            grid = add_caption(&grid, art, caption);
+        }
+
+        let mut text = grid.to_ansi();
+        if let Some(prefix) = &args.comment_prefix {
+            text = format::prefix_lines(&text, prefix);
+        }
+        write_grid(&grid, &text, output_format, &ramp, min, max);
+        return;
     }
 
-    // --blocks implies color mode
-    let color_enabled = args.color || args.blocks;
+    let file = args.files[0].as_str();
+    let mut art = load(file);
 
-    let mut art = match txtr::Txtr::new(
-        &args.file,
-        encoder::select(&args.encoder),
-        red,
-        green,
-        blue,
-        color_enabled,
-    ) {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Error loading image: {}", e);
-            std::process::exit(1);
+    // Recordings and GIFs play every frame of animated input
+    let animated = matches!(
+        output_format,
+        format::Format::Asciicast | format::Format::Gif
+    );
+    let frames = if animated {
+        match animation::load_frames(file) {
+            Ok(frames) => frames,
+            Err(e) => {
+                eprintln!("Error loading image: {}", e);
+                std::process::exit(1);
+            }
         }
+    } else {
+        Vec::new()
     };
 
-    // Validate image dimensions
-    if art.img.width() == 0 || art.img.height() == 0 {
-        eprintln!("Error: Image has zero width or height");
-        std::process::exit(1);
+    // --separate splits the width between its panels
+    let panel_width = |columns: u32| match separation {
+        Some(channels) => {
+            let columns = columns as usize;
+            sheet::thumbnail_width(columns, channels.len(), args.gutter, args.border) as u32
+        }
+        None => columns,
+    };
+
+    // Printed and embossed formats target the page instead of --width,
+    // and dot-based output keeps square dots regardless of --fontsize
+    let (width, fontsize) = if escpos_raster {
+        (
+            args.printer_width.saturating_mul(escpos::DOTS_PER_COLUMN),
+            1.0,
+        )
+    } else if brf {
+        (args.brf_page.0.saturating_mul(2), 1.0)
+    } else if inline_image {
+        // The terminal scales the image to --width, so extra pixels only add bytes
+        let detail = args.width.saturating_mul(graphics::CELL_WIDTH_PX);
+        (
+            detail
+                .min(graphics::MAX_IMAGE_WIDTH_PX)
+                .min(art.img.width()),
+            1.0,
+        )
+    } else if sixel {
+        (args.width.saturating_mul(graphics::CELL_WIDTH_PX), 1.0)
+    } else if output_format == format::Format::EscPos {
+        (
+            panel_width(args.printer_width).saturating_mul(cell_cols),
+            args.fontsize * row_scale,
+        )
+    } else if args.emoji {
+        // Each emoji covers two columns, and the pair is about square
+        ((args.width / 2).max(1), args.fontsize)
+    } else {
+        (
+            panel_width(args.width).saturating_mul(cell_cols),
+            args.fontsize * row_scale,
+        )
+    };
+
+    check_size(&art, width, fontsize);
+
+    if !frames.is_empty() {
+        let mut rendered = Vec::with_capacity(frames.len());
+        for frame in frames {
+            art.set_image(frame.image);
+            prepare(&mut art, &args, width, fontsize);
+            let (mut grid, _) = match separation {
+                Some(channels) => render_separate(&mut art, &args, &render, channels),
+                None => render_grid(&mut art, &args, &render),
+            };
+            if let Some(caption) = &caption {
+                grid = add_caption(&grid, &art, caption);
+            }
+            rendered.push((grid, frame.delay_ms));
+        }
+        write_animation(&rendered, output_format);
+        return;
     }
 
-    if args.outline {
-        art.outline();
+    prepare(&mut art, &args, width, fontsize);
+
+    if sixel {
+        let rgba = art.img.to_rgba8();
+        let pixels: Vec<grid::Rgb> = rgba.pixels().map(|p| (p[0], p[1], p[2])).collect();
+        // Like --blocks, pixels below --alpha-threshold are left to the terminal
+        let opaque: Vec<bool> = rgba
+            .pixels()
+            .map(|p| p[3] >= args.alpha_threshold)
+            .collect();
+        let (w, h) = (rgba.width() as usize, rgba.height() as usize);
+        let visible: Vec<grid::Rgb> = pixels
+            .iter()
+            .zip(&opaque)
+            .filter(|(_, &o)| o)
+            .map(|(&p, _)| p)
+            .collect();
+        let palette = quantize::median_cut(&visible, args.sixel_colors);
+        // A fully transparent image has no palette to map to
+        let indices: Vec<Option<usize>> = if palette.is_empty() {
+            vec![None; w * h]
+        } else {
+            quantize::map_to_palette(&pixels, w, h, &palette, args.dither)
+                .into_iter()
+                .zip(&opaque)
+                .map(|(i, &o)| o.then_some(i))
+                .collect()
+        };
+        print!("{}", graphics::sixel(&indices, w, h, &palette));
+        return;
     }
 
-    if args.invert {
-        art.invert();
+    if inline_image {
+        let encoded = if output_format == format::Format::Kitty {
+            graphics::kitty(&art.img, args.width)
+        } else {
+            graphics::iterm2(&art.img, args.width)
+        };
+        match encoded {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("Error encoding image: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
     }
 
+    if escpos_raster {
+        // Two levels: each dot is either printed or not
+        art.calc_levels(args.dither, 2);
+        let threshold = if args.dither {
+            txtr::Threshold::Fixed(art.midpoint())
+        } else {
+            args.level
+        };
+        // Dark pixels become black dots
+        let mask: Vec<bool> = art
+            .threshold_mask(threshold)
+            .iter()
+            .map(|lit| !lit)
+            .collect();
+        write_bytes(&escpos::raster(&mask, art.width, art.height));
+        return;
+    }
+
+    if brf {
+        art.calc_levels(args.dither, 2);
+        let threshold = if args.dither {
+            txtr::Threshold::Fixed(art.midpoint())
+        } else {
+            args.level
+        };
+        // Dark pixels become raised dots
+        let mask: Vec<bool> = art
+            .threshold_mask(threshold)
+            .iter()
+            .map(|lit| !lit)
+            .collect();
+        let (cells, cols, _) = braille::patterns(&mask, art.width, art.height);
+        let page_rows = args.brf_page.1 as usize;
+        print!("{}", braille::brf(&cells, cols, page_rows));
+        return;
+    }
+
+    let (mut grid, ramp) = match separation {
+        Some(channels) => render_separate(&mut art, &args, &render, channels),
+        None => render_grid(&mut art, &args, &render),
+    };
+    if let Some(caption) = &caption {
+        grid = add_caption(&grid, &art, caption);
+    }
+
+    let mut text = grid.to_ansi();
+    if let Some(prefix) = &args.comment_prefix {
+        text = format::prefix_lines(&text, prefix);
+    }
+    // Character modes have always ended colored text with one more reset
+    if grid.color && !render.blocks && render.mosaic.is_none() {
+        text.push_str(grid::ANSI_RESET);
+    }
+
+    write_grid(&grid, &text, output_format, &ramp, art.min, art.max);
+}
+
+/// Exit if scaling the image to `width` would need too much memory
+fn check_size(art: &txtr::Txtr, width: u32, fontsize: f32) {
     // Prevent memory exhaustion from extremely large resize targets
     const MAX_PIXELS: u64 = 10_000_000; // 10 megapixels
     let aspect_ratio = art.img.height() as f64 / art.img.width() as f64;
-    let new_height = (args.width as f64 * aspect_ratio * args.fontsize as f64) as u64;
-    let pixels = args.width as u64 * new_height.max(1);
+    let new_height = (width as f64 * aspect_ratio * fontsize as f64) as u64;
+    let pixels = width as u64 * new_height.max(1);
     if pixels > MAX_PIXELS {
         eprintln!(
             "Error: Requested dimensions too large ({} pixels, max {})",
@@ -126,16 +648,350 @@ fn main() {
         );
         std::process::exit(1);
     }
+}
+
+/// Apply the image filters, then scale to the output size
+fn prepare(art: &mut txtr::Txtr, args: &cli::Args, width: u32, fontsize: f32) {
+    if args.outline {
+        art.outline();
+    }
+
+    if args.invert {
+        art.invert();
+    }
+
+    art.resize(width, fontsize);
+}
+
+/// Rendering choices resolved from the arguments
+struct Render {
+    chars: String,
+    blocks: bool,
+    mosaic: Option<mosaic::Mosaic>,
+    /// Candidates for --match shape, empty otherwise
+    glyphs: Vec<(char, font::Bitmap)>,
+    background: txtr::Background,
+    /// Characters of --text-source, ready to flow
+    text: Option<Vec<char>>,
+    /// --two-tone glyphs for lit and unlit pixels
+    two_tone: Option<(char, char)>,
+    lines: Option<lines::LineStyle>,
+    /// --mask subject, and how to draw the rest
+    mask: Option<(txtr::MaskSource, Outside)>,
+}
+
+/// How cells outside the --mask subject are drawn
+struct Outside {
+    /// Ramp, a single space when they're left blank
+    chars: String,
+    encoder: String,
+    color: bool,
+}
+
+/// Quantize brightness and lay the image out as cells; also returns the ramp used
+fn render_grid(art: &mut txtr::Txtr, args: &cli::Args, render: &Render) -> (grid::Grid, String) {
+    let chars = render.chars.as_str();
+    let (blocks, mosaic, glyphs) = (render.blocks, render.mosaic, &render.glyphs);
+    if args.braille && !blocks {
+        art.calc_levels(args.dither, 2);
+        let threshold = if args.dither {
+            txtr::Threshold::Fixed(art.midpoint())
+        } else {
+            args.level
+        };
+        return (
+            art.grid_braille(threshold),
+            braille::to_unicode(0xff).to_string(),
+        );
+    }
+
+    if args.emoji && !blocks {
+        art.calc_levels(false, 2);
+        return (art.grid_emoji(), emoji::all());
+    }
+
+    if args.edges && !blocks {
+        art.calc_levels(false, 2);
+        let grid = art.grid_edges(args.edge_threshold, args.edge_color);
+        return (grid, edges::EDGE_CHARS.to_string());
+    }
+
+    if let Some(style) = render.lines.filter(|_| !blocks) {
+        art.calc_levels(false, 2);
+        let grid = art.grid_lines(args.edge_threshold, style, args.edge_color);
+        return (grid, style.chars());
+    }
 
-    art.resize(args.width, args.fontsize);
+    // Shape matching has no ramp; --glyphs is empty unless it was requested
+    if !glyphs.is_empty() && !blocks {
+        art.calc_levels(false, 2);
+        return (
+            art.grid_shape(glyphs),
+            glyphs.iter().map(|(c, _)| c).collect(),
+        );
+    }
+
+    // Edges come from the undithered image, where dithering can't read as texture
+    let edges = (args.edge_overlay && !blocks).then(|| {
+        art.calc_levels(false, 2);
+        art.edge_glyphs(args.edge_threshold)
+    });
 
-    art.calc_levels(args.dither, char_count);
+    // The rest of a --mask is rendered on its own and composited in
+    let outside = render
+        .mask
+        .as_ref()
+        .filter(|_| !blocks)
+        .map(|(source, outside)| {
+            art.calc_levels(false, 2);
+            let inside = art.region_mask(source);
+            (inside, render_outside(art, args, outside))
+        });
+
+    // --attributes multiplies the ramp by dim, bold and reverse video
+    let shades = args.attributes.then(|| txtr::attribute_ramp(chars));
+    let levels = if render.two_tone.is_some() {
+        2
+    } else {
+        shades.as_ref().map_or(chars.chars().count(), Vec::len)
+    };
+    art.calc_levels(args.dither, levels);
 
-    if args.blocks {
-        art.print_blocks();
-    } else if args.print_in_order {
-        art.print_in_order(&chars, args.level);
+    if blocks {
+        (
+            art.grid_blocks(args.alpha_threshold),
+            txtr::UPPER_HALF_BLOCK.to_string(),
+        )
+    } else if let Some(mosaic) = mosaic {
+        (art.grid_mosaic(mosaic), mosaic.glyph(u8::MAX).to_string())
     } else {
-        art.print_by_level(&chars);
+        let mut ramp = chars.to_string();
+        let mut grid = if let Some((lit, unlit)) = render.two_tone {
+            let threshold = if args.dither {
+                txtr::Threshold::Fixed(art.midpoint())
+            } else {
+                args.level
+            };
+            ramp = [unlit, lit].iter().collect();
+            art.grid_two_tone(lit, unlit, threshold)
+        } else if let Some(text) = &render.text {
+            art.grid_text(text, args.level, args.keep_words)
+        } else if args.print_in_order {
+            art.grid_in_order(chars, args.level)
+        } else if let Some(shades) = &shades {
+            art.grid_by_shade(shades)
+        } else {
+            art.grid_by_level(chars)
+        };
+        if let Some((inside, outside)) = &outside {
+            grid.composite(outside, inside);
+        }
+        art.fill_background(&mut grid, render.background);
+        if let Some(edges) = edges {
+            art.overlay_edges(&mut grid, &edges, args.edge_color);
+        }
+        (grid, ramp)
+    }
+}
+
+/// Render the image once per --separate channel, each through its own
+/// encoder, as labeled panels side by side; also returns the ramp used
+fn render_separate(
+    art: &mut txtr::Txtr,
+    args: &cli::Args,
+    render: &Render,
+    channels: &[&str],
+) -> (grid::Grid, String) {
+    let mut ramp = String::new();
+    let panels: Vec<grid::Grid> = channels
+        .iter()
+        .map(|&channel| {
+            let image_channel = std::mem::replace(&mut art.channel, encoder::select(channel));
+            let (grid, used) = render_grid(art, args, render);
+            art.channel = image_channel;
+            ramp = used;
+            sheet::tile(&grid, channel, grid.width, args.border)
+        })
+        .collect();
+    (sheet::layout(&panels, panels.len(), args.gutter), ramp)
+}
+
+/// A --caption rendered in its font, ready to place
+struct Caption {
+    grid: grid::Grid,
+    position: figlet::Position,
+    gradient: bool,
+}
+
+/// Blank columns between the art and a caption beside it
+const CAPTION_GAP: usize = 2;
+
+/// Place the caption next to the art, colored from the image with --caption-gradient
+fn add_caption(art_grid: &grid::Grid, art: &txtr::Txtr, caption: &Caption) -> grid::Grid {
+    let mut text = caption.grid.clone();
+    if caption.gradient {
+        let colors = art.column_colors(text.width);
+        for row in text.cells.chunks_mut(text.width.max(1)) {
+            for (cell, &color) in row.iter_mut().zip(&colors) {
+                if cell.glyph != ' ' {
+                    cell.fg = Some(color);
+                }
+            }
+        }
+        text.color = true;
+    }
+    match caption.position {
+        figlet::Position::Below => art_grid.above(&text),
+        figlet::Position::Above => text.above(art_grid),
+        figlet::Position::Left => text.beside(art_grid, CAPTION_GAP),
+        figlet::Position::Right => art_grid.beside(&text, CAPTION_GAP),
+    }
+}
+
+/// Render the cells outside a --mask with their own ramp, encoder and color,
+/// leaving the image's settings as they were
+fn render_outside(art: &mut txtr::Txtr, args: &cli::Args, outside: &Outside) -> grid::Grid {
+    let channel = std::mem::replace(&mut art.channel, encoder::select(&outside.encoder));
+    let color = std::mem::replace(&mut art.color_enabled, outside.color);
+    art.calc_levels(args.dither, outside.chars.chars().count());
+    let grid = art.grid_by_level(&outside.chars);
+    (art.channel, art.color_enabled) = (channel, color);
+    grid
+}
+
+/// Write rendered frames, each paired with its delay in milliseconds
+fn write_animation(frames: &[(grid::Grid, u32)], output_format: format::Format) {
+    if output_format == format::Format::Gif {
+        let images: Vec<_> = frames
+            .iter()
+            .map(|(grid, delay_ms)| (raster::to_image(&grid.resolve_attributes()), *delay_ms))
+            .collect();
+        match graphics::gif(&images) {
+            Ok(gif) => write_bytes(&gif),
+            Err(e) => {
+                eprintln!("Error encoding image: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let (cols, rows) = (frames[0].0.width, frames[0].0.height);
+        let texts: Vec<(String, u32)> = frames
+            .iter()
+            .map(|(grid, delay_ms)| (grid.to_ansi(), *delay_ms))
+            .collect();
+        print!("{}", format::to_asciicast(&texts, cols, rows));
+    }
+}
+
+/// Re-export existing ANSI art through the same cell grid images render to
+fn convert(args: &cli::ConvertArgs) {
+    let output_format = format::select(&args.format);
+    if matches!(
+        output_format,
+        format::Format::Brf
+            | format::Format::Kitty
+            | format::Format::Iterm2
+            | format::Format::Sixel
+    ) {
+        eprintln!(
+            "Error: --format {} needs an image and is not supported by convert.",
+            args.format
+        );
+        std::process::exit(1);
+    }
+    if args.wrap == Some(0) {
+        eprintln!("Error: --wrap must be at least 1.");
+        std::process::exit(1);
+    }
+
+    let bytes = if args.file == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        std::fs::read(&args.file)
+    };
+    let bytes = match bytes {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    };
+
+    // SAUCE metadata and DOS padding follow an end-of-file (SUB) byte
+    let bytes = bytes.split(|&b| b == 0x1a).next().unwrap_or_default();
+    let (text, cp437) = match std::str::from_utf8(bytes) {
+        Ok(text) if !args.cp437 => (text.to_string(), false),
+        _ => (cp437::decode(bytes), true),
+    };
+    let wrap = args.wrap.or(if cp437 { Some(80) } else { None });
+
+    let depth = ansi::select_depth(&args.colors);
+    let mut grid = match ansi::parse(&text, wrap) {
+        Ok(grid) => grid,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    };
+    grid.requantize(depth);
+
+    let levels = grid.cells.iter().map(|cell| cell.level);
+    let (min, max) = (levels.clone().min().unwrap_or(0), levels.max().unwrap_or(0));
+    write_grid(
+        &grid,
+        &grid.to_ansi_with(depth),
+        output_format,
+        "",
+        min,
+        max,
+    );
+}
+
+/// Write a rendered grid in one of the cell-based output formats
+fn write_grid(
+    grid: &grid::Grid,
+    text: &str,
+    output_format: format::Format,
+    ramp: &str,
+    min: usize,
+    max: usize,
+) {
+    match output_format {
+        format::Format::Text => print!("{}", text),
+        format::Format::Json => {
+            print!(
+                "{}",
+                format::to_json(&grid.resolve_attributes(), ramp, min, max)
+            )
+        }
+        format::Format::EscPos => write_bytes(&escpos::text(grid)),
+        format::Format::Html => print!("{}", format::to_html(&grid.resolve_attributes())),
+        format::Format::Svg => print!("{}", format::to_svg(&grid.resolve_attributes())),
+        format::Format::Png => {
+            let grid = grid.resolve_attributes();
+            let img = image::DynamicImage::ImageRgb8(raster::to_image(&grid));
+            match graphics::encode_png(&img) {
+                Ok(png) => write_bytes(&png),
+                Err(e) => {
+                    eprintln!("Error encoding image: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        format::Format::Asciicast | format::Format::Gif => {
+            write_animation(&[(grid.clone(), 0)], output_format)
+        }
+        // Unsupported image protocols fell back to blocks
+        format::Format::Kitty | format::Format::Iterm2 => print!("{}", text),
+        lang => print!("{}", format::to_source(text, lang)),
+    }
+}
+
+fn write_bytes(bytes: &[u8]) {
+    let mut stdout = std::io::stdout().lock();
+    if let Err(e) = stdout.write_all(bytes).and_then(|_| stdout.flush()) {
+        eprintln!("Error writing output: {}", e);
+        std::process::exit(1);
     }
 }
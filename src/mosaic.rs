@@ -0,0 +1,157 @@
+pub const VALID_MOSAICS: &[&str] = &["quadrant", "sextant", "octant"];
+
+/// Block mosaics that split a character cell into 2 columns of sub-pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mosaic {
+    Quadrant,
+    Sextant,
+    Octant,
+}
+
+pub fn select(name: &str) -> Mosaic {
+    match name {
+        "quadrant" => Mosaic::Quadrant,
+        "sextant" => Mosaic::Sextant,
+        "octant" => Mosaic::Octant,
+        _ => {
+            eprintln!(
+                "Warning: unknown mosaic '{}', using 'quadrant'. Valid mosaics: {}",
+                name,
+                VALID_MOSAICS.join(", ")
+            );
+            Mosaic::Quadrant
+        }
+    }
+}
+
+/// Quadrant blocks indexed by pattern: bit 0 top-left, bit 1 top-right,
+/// bit 2 bottom-left, bit 3 bottom-right
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Sextants U+1FB00.. skip the patterns that already exist as half and full blocks
+const SEXTANT_BASE: u32 = 0x1FB00;
+const SEXTANT_EXISTING: [(u8, char); 4] = [(0, ' '), (21, '▌'), (42, '▐'), (63, '█')];
+
+/// Octants U+1CD00.. likewise skip patterns encoded elsewhere: quadrants,
+/// eighth blocks and a few Symbols for Legacy Computing characters
+const OCTANT_BASE: u32 = 0x1CD00;
+const OCTANT_EXISTING: [(u8, char); 26] = [
+    (0, ' '),
+    (1, '\u{1CEA8}'),
+    (2, '\u{1CEAB}'),
+    (3, '\u{1FB82}'),
+    (5, '▘'),
+    (10, '▝'),
+    (15, '▀'),
+    (20, '\u{1FBE6}'),
+    (40, '\u{1FBE7}'),
+    (63, '\u{1FB85}'),
+    (64, '\u{1CEA3}'),
+    (80, '▖'),
+    (85, '▌'),
+    (90, '▞'),
+    (95, '▛'),
+    (128, '\u{1CEA0}'),
+    (160, '▗'),
+    (165, '▚'),
+    (170, '▐'),
+    (175, '▜'),
+    (192, '▂'),
+    (240, '▄'),
+    (245, '▙'),
+    (250, '▟'),
+    (252, '▆'),
+    (255, '█'),
+];
+
+/// Look up a pattern in a block whose code points run in pattern order,
+/// minus the patterns that were already encoded elsewhere
+fn sequential(base: u32, existing: &[(u8, char)], pattern: u8) -> char {
+    if let Some(&(_, c)) = existing.iter().find(|&&(p, _)| p == pattern) {
+        return c;
+    }
+    let skipped = existing.iter().filter(|&&(p, _)| p < pattern).count() as u32;
+    char::from_u32(base + pattern as u32 - skipped).unwrap_or('?')
+}
+
+impl Mosaic {
+    /// Sub-pixels per cell as (columns, rows)
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            Mosaic::Quadrant => (2, 2),
+            Mosaic::Sextant => (2, 3),
+            Mosaic::Octant => (2, 4),
+        }
+    }
+
+    /// Glyph for a row-major sub-pixel pattern (bit 0 top-left, bit 1 top-right, ...)
+    pub fn glyph(self, pattern: u8) -> char {
+        match self {
+            Mosaic::Quadrant => QUADRANTS[(pattern & 0x0f) as usize],
+            Mosaic::Sextant => sequential(SEXTANT_BASE, &SEXTANT_EXISTING, pattern & 0x3f),
+            Mosaic::Octant => sequential(OCTANT_BASE, &OCTANT_EXISTING, pattern),
+        }
+    }
+
+    /// Sub-pixel pattern drawn by a glyph, if it belongs to this mosaic
+    pub fn pattern(self, glyph: char) -> Option<u8> {
+        let (w, h) = self.cell_size();
+        (0..1u16 << (w * h))
+            .map(|p| p as u8)
+            .find(|&p| self.glyph(p) == glyph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_mosaics() {
+        assert_eq!(select("sextant"), Mosaic::Sextant);
+        assert_eq!(select("octant"), Mosaic::Octant);
+        assert_eq!(select("hexant"), Mosaic::Quadrant);
+    }
+
+    #[test]
+    fn quadrant_glyphs() {
+        assert_eq!(Mosaic::Quadrant.glyph(0b0001), '▘');
+        assert_eq!(Mosaic::Quadrant.glyph(0b0011), '▀');
+        assert_eq!(Mosaic::Quadrant.glyph(0b1001), '▚');
+    }
+
+    #[test]
+    fn sextant_glyphs() {
+        assert_eq!(Mosaic::Sextant.glyph(1), '\u{1FB00}');
+        assert_eq!(Mosaic::Sextant.glyph(20), '\u{1FB13}');
+        assert_eq!(Mosaic::Sextant.glyph(21), '▌');
+        assert_eq!(Mosaic::Sextant.glyph(22), '\u{1FB14}');
+        assert_eq!(Mosaic::Sextant.glyph(62), '\u{1FB3B}');
+        assert_eq!(Mosaic::Sextant.glyph(63), '█');
+    }
+
+    #[test]
+    fn octant_glyphs() {
+        assert_eq!(Mosaic::Octant.glyph(4), '\u{1CD00}');
+        assert_eq!(Mosaic::Octant.glyph(0b0011_0000), '\u{1CD27}');
+        assert_eq!(Mosaic::Octant.glyph(254), '\u{1CDE5}');
+        assert_eq!(Mosaic::Octant.glyph(0b1111_0000), '▄');
+    }
+
+    #[test]
+    fn pattern_inverts_glyph() {
+        assert_eq!(Mosaic::Sextant.pattern('\u{1FB14}'), Some(22));
+        assert_eq!(Mosaic::Octant.pattern('▄'), Some(0b1111_0000));
+        assert_eq!(Mosaic::Quadrant.pattern('a'), None);
+    }
+
+    #[test]
+    fn octant_glyphs_are_distinct() {
+        let mut glyphs: Vec<char> = (0..=255).map(|p| Mosaic::Octant.glyph(p)).collect();
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        assert_eq!(glyphs.len(), 256);
+    }
+}
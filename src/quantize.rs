@@ -0,0 +1,249 @@
+use crate::dither;
+use crate::grid::Rgb;
+use std::collections::HashMap;
+
+/// Build a palette of at most `max_colors` colors using median cut: repeatedly
+/// split the box with the widest channel range at its median, then average each box.
+pub fn median_cut(colors: &[Rgb], max_colors: usize) -> Vec<Rgb> {
+    if colors.is_empty() || max_colors == 0 {
+        return vec![];
+    }
+
+    let mut boxes: Vec<Vec<Rgb>> = vec![colors.to_vec()];
+    while boxes.len() < max_colors {
+        // Pick the box with the widest single-channel spread
+        let (index, channel, spread) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let (channel, spread) = widest_channel(b);
+                (i, channel, spread)
+            })
+            .max_by_key(|&(_, _, spread)| spread)
+            .unwrap_or((0, 0, 0));
+        if spread == 0 {
+            break; // every box is a single color
+        }
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|c| channel_value(*c, channel));
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| average(b)).collect()
+}
+
+fn channel_value(c: Rgb, channel: usize) -> u8 {
+    match channel {
+        0 => c.0,
+        1 => c.1,
+        _ => c.2,
+    }
+}
+
+fn widest_channel(colors: &[Rgb]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = colors.iter().map(|&c| channel_value(c, channel));
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, spread)| spread)
+        .unwrap_or((0, 0))
+}
+
+/// Mean of a set of colors; black when empty
+pub fn average(colors: &[Rgb]) -> Rgb {
+    let n = colors.len().max(1) as u64;
+    let (r, g, b) = colors.iter().fold((0u64, 0u64, 0u64), |acc, c| {
+        (acc.0 + c.0 as u64, acc.1 + c.1 as u64, acc.2 + c.2 as u64)
+    });
+    ((r / n) as u8, (g / n) as u8, (b / n) as u8)
+}
+
+/// Rounds of 2-means refinement; a cell has at most 8 sub-pixels, so this converges fast
+const SPLIT_ROUNDS: usize = 4;
+
+/// Split colors into two clusters with 2-means, seeded from the extremes of the
+/// widest channel. Returns which colors belong to the first cluster and both means.
+pub fn split_two(colors: &[Rgb]) -> (Vec<bool>, Rgb, Rgb) {
+    let (channel, spread) = widest_channel(colors);
+    if spread == 0 {
+        let color = colors.first().copied().unwrap_or((0, 0, 0));
+        return (vec![false; colors.len()], color, color);
+    }
+
+    let key = |c: &&Rgb| channel_value(**c, channel);
+    let mut first = *colors.iter().max_by_key(key).unwrap_or(&(0, 0, 0));
+    let mut second = *colors.iter().min_by_key(key).unwrap_or(&(0, 0, 0));
+    let mut members = vec![false; colors.len()];
+    for _ in 0..SPLIT_ROUNDS {
+        for (member, &c) in members.iter_mut().zip(colors) {
+            *member = distance(c, first) < distance(c, second);
+        }
+        let cluster = |wanted: bool| -> Vec<Rgb> {
+            colors
+                .iter()
+                .zip(&members)
+                .filter(|(_, &member)| member == wanted)
+                .map(|(&c, _)| c)
+                .collect()
+        };
+        first = average(&cluster(true));
+        second = average(&cluster(false));
+    }
+    (members, first, second)
+}
+
+/// Index of the palette entry closest to `color` (squared RGB distance)
+pub fn nearest(palette: &[Rgb], color: Rgb) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| distance(**p, color))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+pub fn distance(a: Rgb, b: Rgb) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+/// CIELAB coordinates of an sRGB color (D65 white), where straight-line
+/// distance roughly tracks how different two colors look
+pub fn lab((r, g, b): Rgb) -> [f64; 3] {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Map every pixel to its nearest palette index, optionally with
+/// Floyd-Steinberg dithering to hide banding
+pub fn map_to_palette(
+    pixels: &[Rgb],
+    width: usize,
+    height: usize,
+    palette: &[Rgb],
+    dither: bool,
+) -> Vec<usize> {
+    // Photos repeat colors a lot, so remember lookups
+    let mut cache: HashMap<Rgb, usize> = HashMap::new();
+    let mut lookup = |c: Rgb| *cache.entry(c).or_insert_with(|| nearest(palette, c));
+
+    if !dither {
+        return pixels.iter().map(|&c| lookup(c)).collect();
+    }
+
+    let mut values: Vec<[f64; 3]> = pixels
+        .iter()
+        .map(|&(r, g, b)| [r as f64, g as f64, b as f64])
+        .collect();
+    let mut indices = Vec::with_capacity(pixels.len());
+    dither::floyd_steinberg(&mut values, width, height, |[r, g, b]| {
+        let clamp = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+        let index = lookup((clamp(r), clamp(g), clamp(b)));
+        indices.push(index);
+        let (r, g, b) = palette[index];
+        [r as f64, g as f64, b as f64]
+    });
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_cut_respects_max_colors() {
+        let colors: Vec<Rgb> = (0..=255).map(|v| (v, 255 - v, v / 2)).collect();
+        let palette = median_cut(&colors, 16);
+        assert_eq!(palette.len(), 16);
+    }
+
+    #[test]
+    fn median_cut_stops_at_distinct_colors() {
+        let colors = vec![(255, 0, 0); 10]
+            .into_iter()
+            .chain(vec![(0, 0, 255); 10])
+            .collect::<Vec<_>>();
+        let mut palette = median_cut(&colors, 256);
+        palette.sort();
+        assert_eq!(palette, vec![(0, 0, 255), (255, 0, 0)]);
+    }
+
+    #[test]
+    fn lab_lightness_and_hue() {
+        let [l, a, b] = lab((0, 0, 0));
+        assert!(l.abs() < 0.01 && a.abs() < 0.01 && b.abs() < 0.01);
+        let [l, a, b] = lab((255, 255, 255));
+        assert!((l - 100.0).abs() < 0.01 && a.abs() < 0.1 && b.abs() < 0.1);
+        // Red sits on the +a (red-green) axis, blue on -b
+        assert!(lab((255, 0, 0))[1] > 70.0);
+        assert!(lab((0, 0, 255))[2] < -100.0);
+    }
+
+    #[test]
+    fn median_cut_empty_input() {
+        assert!(median_cut(&[], 8).is_empty());
+    }
+
+    #[test]
+    fn split_two_separates_clusters() {
+        let colors = [(250, 0, 0), (0, 0, 240), (240, 10, 0), (10, 0, 230)];
+        let (members, first, second) = split_two(&colors);
+        assert_eq!(members, vec![true, false, true, false]);
+        assert_eq!(first, (245, 5, 0));
+        assert_eq!(second, (5, 0, 235));
+    }
+
+    #[test]
+    fn split_two_uniform_colors() {
+        let (members, first, second) = split_two(&[(7, 8, 9); 4]);
+        assert!(members.iter().all(|&m| !m));
+        assert_eq!(first, (7, 8, 9));
+        assert_eq!(second, (7, 8, 9));
+    }
+
+    #[test]
+    fn nearest_picks_closest() {
+        let palette = [(0, 0, 0), (255, 255, 255), (255, 0, 0)];
+        assert_eq!(nearest(&palette, (10, 10, 10)), 0);
+        assert_eq!(nearest(&palette, (200, 30, 30)), 2);
+        assert_eq!(nearest(&palette, (240, 240, 250)), 1);
+    }
+
+    #[test]
+    fn map_to_palette_dithers_mid_gray() {
+        let palette = [(0, 0, 0), (255, 255, 255)];
+        let pixels = vec![(128, 128, 128); 16];
+        let plain = map_to_palette(&pixels, 4, 4, &palette, false);
+        assert!(plain.iter().all(|&i| i == plain[0]));
+        let dithered = map_to_palette(&pixels, 4, 4, &palette, true);
+        assert!(dithered.contains(&0) && dithered.contains(&1));
+    }
+}
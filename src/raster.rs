@@ -0,0 +1,56 @@
+use crate::ansi::{DEFAULT_BG, DEFAULT_FG};
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::grid::Grid;
+use image::{Rgb, RgbImage};
+
+/// Draw the grid with the built-in 8x13 bitmap font, one glyph per cell
+pub fn to_image(grid: &Grid) -> RgbImage {
+    let width = (grid.width as u32 * GLYPH_WIDTH).max(1);
+    let height = (grid.height as u32 * GLYPH_HEIGHT).max(1);
+    let (r, g, b) = DEFAULT_BG;
+    let mut img = RgbImage::from_pixel(width, height, Rgb([r, g, b]));
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let (fr, fg, fb) = cell.fg.unwrap_or(DEFAULT_FG);
+            let (br, bg, bb) = cell.bg.unwrap_or(DEFAULT_BG);
+            let bitmap = font::glyph(cell.glyph);
+            for (dy, bits) in bitmap.iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    let lit = bits & (0x80 >> dx) != 0;
+                    let color = if lit { [fr, fg, fb] } else { [br, bg, bb] };
+                    img.put_pixel(
+                        x as u32 * GLYPH_WIDTH + dx,
+                        y as u32 * GLYPH_HEIGHT + dy as u32,
+                        Rgb(color),
+                    );
+                }
+            }
+        }
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Cell;
+
+    #[test]
+    fn cells_fill_with_colors() {
+        let mut grid = Grid::new(2, 1, true);
+        grid.push(Cell {
+            glyph: '█',
+            fg: Some((255, 0, 0)),
+            ..Cell::blank()
+        });
+        grid.push(Cell {
+            bg: Some((0, 0, 255)),
+            ..Cell::blank()
+        });
+        let img = to_image(&grid);
+        assert_eq!(img.dimensions(), (16, 13));
+        assert_eq!(img.get_pixel(3, 6), &Rgb([255, 0, 0]));
+        assert_eq!(img.get_pixel(11, 6), &Rgb([0, 0, 255]));
+    }
+}
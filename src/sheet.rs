@@ -0,0 +1,127 @@
+use crate::grid::Grid;
+
+/// Columns and rows for a contact sheet of `count` images without --grid:
+/// as close to square as the count allows
+pub fn default_layout(count: usize) -> (usize, usize) {
+    let cols = (1..).find(|cols| cols * cols >= count).unwrap_or(1);
+    (cols, count.div_ceil(cols).max(1))
+}
+
+/// Columns each thumbnail gets when `cols` of them, with their borders and
+/// the gutters between them, fill `width`
+pub fn thumbnail_width(width: usize, cols: usize, gutter: usize, border: bool) -> usize {
+    let frame = if border { 2 } else { 0 };
+    let gutters = gutter.saturating_mul(cols.saturating_sub(1));
+    (width.saturating_sub(gutters) / cols.max(1))
+        .saturating_sub(frame)
+        .max(1)
+}
+
+/// The file name of a path, for a thumbnail's label
+pub fn label(path: &str) -> String {
+    std::path::Path::new(path).file_name().map_or_else(
+        || path.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// A thumbnail `width` columns wide with its label centered above it,
+/// shortened with an ellipsis when it doesn't fit, and optionally a box
+/// around the thumbnail
+pub fn tile(thumbnail: &Grid, label: &str, width: usize, border: bool) -> Grid {
+    let frame = if border { 2 } else { 0 };
+    let outer = width + frame;
+    let label: String = if label.chars().count() > outer {
+        label.chars().take(outer - 1).chain(['…']).collect()
+    } else {
+        label.to_string()
+    };
+    let label = Grid::from_lines(&[label]);
+
+    let mut tile = Grid::blank(outer, 1 + thumbnail.height + frame, false);
+    tile.draw(&label, (outer - label.width) / 2, 0);
+    if border {
+        let rule = "─".repeat(width);
+        let mut lines = vec![format!("┌{}┐", rule)];
+        let side = format!("│{}│", " ".repeat(width));
+        lines.extend(std::iter::repeat_n(side, thumbnail.height));
+        lines.push(format!("└{}┘", rule));
+        tile.draw(&Grid::from_lines(&lines), 0, 1);
+    }
+    let x = frame / 2 + width.saturating_sub(thumbnail.width) / 2;
+    tile.draw(thumbnail, x, 1 + frame / 2);
+    tile
+}
+
+/// Lay tiles out left to right, top to bottom, `cols` to a row with `gutter`
+/// blank columns between them. Cells are about twice as tall as they are
+/// wide, so rows are half as far apart. Each row is as tall as its tallest tile.
+pub fn layout(tiles: &[Grid], cols: usize, gutter: usize) -> Grid {
+    let cols = cols.clamp(1, tiles.len().max(1));
+    let tile_width = tiles.iter().map(|tile| tile.width).max().unwrap_or(0);
+    let row_gap = gutter.div_ceil(2);
+    let heights: Vec<usize> = tiles
+        .chunks(cols)
+        .map(|row| row.iter().map(|tile| tile.height).max().unwrap_or(0))
+        .collect();
+
+    let width = cols * tile_width + (cols - 1) * gutter;
+    let height = heights.iter().sum::<usize>() + heights.len().saturating_sub(1) * row_gap;
+    let mut sheet = Grid::blank(width, height, false);
+    let mut y = 0;
+    for (row, row_height) in tiles.chunks(cols).zip(heights) {
+        for (col, tile) in row.iter().enumerate() {
+            sheet.draw(tile, col * (tile_width + gutter), y);
+        }
+        y += row_height + row_gap;
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(grid: &Grid) -> Vec<String> {
+        grid.to_ansi().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn default_layout_is_about_square() {
+        assert_eq!(default_layout(1), (1, 1));
+        assert_eq!(default_layout(2), (2, 1));
+        assert_eq!(default_layout(5), (3, 2));
+        assert_eq!(default_layout(9), (3, 3));
+    }
+
+    #[test]
+    fn thumbnails_fill_the_width() {
+        assert_eq!(thumbnail_width(80, 3, 2, false), 25);
+        assert_eq!(thumbnail_width(80, 3, 2, true), 23);
+        assert_eq!(thumbnail_width(4, 8, 2, true), 1);
+    }
+
+    #[test]
+    fn label_is_the_file_name() {
+        assert_eq!(label("assets/castle.jpg"), "castle.jpg");
+        assert_eq!(label("logo.png"), "logo.png");
+    }
+
+    #[test]
+    fn tile_with_border_and_long_label() {
+        let thumbnail = Grid::from_lines(&["ab".to_string(), "cd".to_string()]);
+        let tile = tile(&thumbnail, "castle.jpg", 3, true);
+        assert_eq!(
+            lines(&tile),
+            vec!["cast…", "┌───┐", "│ab │", "│cd │", "└───┘"]
+        );
+    }
+
+    #[test]
+    fn layout_rows_and_gutters() {
+        let tall = Grid::from_lines(&["a".to_string(), "a".to_string()]);
+        let short = Grid::from_lines(&["b".to_string()]);
+        let sheet = layout(&[tall, short.clone(), short], 2, 2);
+        assert_eq!(lines(&sheet), vec!["a  b", "a   ", "    ", "b   "]);
+    }
+}
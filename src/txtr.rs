@@ -1,4 +1,13 @@
+use crate::braille;
+use crate::dither;
+use crate::edges;
+use crate::emoji;
 use crate::encoder;
+use crate::font::{self, Bitmap, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::grid::{Attr, Cell, Grid, Rgb};
+use crate::lines::{self, LineStyle};
+use crate::mosaic::Mosaic;
+use crate::quantize;
 use image::{DynamicImage, GenericImageView, ImageError};
 use std::path::Path;
 
@@ -13,6 +22,153 @@ pub const DEFAULT_CHARS: &str = "#$%{/;:,.. ";
 
 pub const VALID_RAMPS: &[&str] = &["standard", "dense", "blocks", "simple"];
 
+pub const VALID_MATCHES: &[&str] = &["level", "shape"];
+
+/// How character modes pick a glyph for each cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Match {
+    /// One pixel per cell, mapped through the ramp by brightness
+    Level,
+    /// A cell of pixels compared against glyph bitmaps
+    Shape,
+}
+
+pub const VALID_BACKGROUNDS: &[&str] = &["none", "dim", "only"];
+
+/// Background color character modes give each cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    None,
+    /// A darker tone of the cell's color behind the glyph
+    Dim,
+    /// Spaces in the cell's color, dropping the glyphs
+    Only,
+}
+
+/// How much of the cell's color a dim background keeps
+const BACKGROUND_DIM: f64 = 0.4;
+
+pub fn select_background(name: &str) -> Background {
+    match name {
+        "none" => Background::None,
+        "dim" => Background::Dim,
+        "only" => Background::Only,
+        _ => {
+            eprintln!(
+                "Warning: unknown background '{}', using 'none'. Valid backgrounds: {}",
+                name,
+                VALID_BACKGROUNDS.join(", ")
+            );
+            Background::None
+        }
+    }
+}
+
+pub fn select_match(name: &str) -> Match {
+    match name {
+        "level" => Match::Level,
+        "shape" => Match::Shape,
+        _ => {
+            eprintln!(
+                "Warning: unknown match mode '{}', using 'level'. Valid match modes: {}",
+                name,
+                VALID_MATCHES.join(", ")
+            );
+            Match::Level
+        }
+    }
+}
+
+/// Where two-level modes split lit pixels from unlit ones
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Threshold {
+    /// Lit above a fixed level
+    Fixed(usize),
+    /// One level for the whole image, picked by Otsu's method
+    Otsu,
+    /// Lit when brighter than the pixels around it
+    Adaptive,
+}
+
+/// Where --mask finds the subject of the image
+pub enum MaskSource {
+    /// Light areas of a mask image, scaled to fit
+    Image(DynamicImage),
+    /// Pixels at least this opaque
+    Alpha(u8),
+    /// Pixels above a brightness threshold
+    Level(Threshold),
+}
+
+/// Sets the neighborhood adaptive thresholds average: its half-width is the
+/// image's longer side divided by this, and at least one pixel
+const ADAPTIVE_WINDOW: usize = 16;
+
+/// How far below its neighborhood mean a pixel can be and still count as lit
+const ADAPTIVE_OFFSET: f64 = 0.15;
+
+/// Turn a document into the characters --text-source flows through the image:
+/// line breaks, tabs and other whitespace become spaces, or with collapse one
+/// space per run of them, and control characters are dropped
+pub fn flowing_text(text: &str, collapse: bool) -> Vec<char> {
+    let mut chars: Vec<char> = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !(collapse && chars.last().is_none_or(|&last| last == ' ')) {
+                chars.push(' ');
+            }
+        } else if !c.is_control() {
+            chars.push(c);
+        }
+    }
+    if collapse && chars.last() == Some(&' ') {
+        chars.pop();
+    }
+    chars
+}
+
+/// How bright each attribute draws a glyph, against xterm's defaults: normal
+/// text is color 7 (90% white), dim text half that, and bold text is bright
+/// white with strokes about a pixel wider
+const ATTRIBUTE_WEIGHTS: [(Attr, f64); 3] =
+    [(Attr::Normal, 0.9), (Attr::Dim, 0.45), (Attr::Bold, 1.2)];
+
+/// Shades a cell can show apart from color, closer than this count as the same
+const SHADE_TOLERANCE: f64 = 0.005;
+
+/// Extend a ramp with dim, bold and reverse versions of its characters,
+/// ordered by how much light each one puts on screen: the glyph's pixel
+/// coverage in the built-in font times the attribute's brightness, or for
+/// reverse video the uncovered part of the cell. Ramps that run from dense to
+/// sparse (like the default) keep that direction.
+pub fn attribute_ramp(chars: &str) -> Vec<(char, Attr)> {
+    let coverage = |c: char| {
+        let lit: u32 = font::glyph(c).iter().map(|row| row.count_ones()).sum();
+        lit as f64 / (GLYPH_WIDTH * GLYPH_HEIGHT) as f64
+    };
+
+    let mut shades: Vec<(f64, char, Attr)> = Vec::new();
+    for c in chars.chars() {
+        let covered = coverage(c);
+        for (attr, weight) in ATTRIBUTE_WEIGHTS {
+            shades.push((covered * weight, c, attr));
+        }
+        shades.push(((1.0 - covered) * ATTRIBUTE_WEIGHTS[0].1, c, Attr::Reverse));
+    }
+    // Stable, so of equal shades the earliest character in its plainest form wins
+    shades.sort_by(|a, b| a.0.total_cmp(&b.0));
+    shades.dedup_by(|b, a| b.0 - a.0 < SHADE_TOLERANCE);
+
+    let mut ramp: Vec<(char, Attr)> = shades.into_iter().map(|(_, c, attr)| (c, attr)).collect();
+    let (first, last) = (chars.chars().next(), chars.chars().last());
+    if let (Some(first), Some(last)) = (first, last) {
+        if coverage(first) > coverage(last) {
+            ramp.reverse();
+        }
+    }
+    ramp
+}
+
 pub fn get_ramp(name: &str) -> &'static str {
     match name {
         "standard" => RAMP_STANDARD,
@@ -34,6 +190,7 @@ pub fn get_ramp(name: &str) -> &'static str {
 pub struct PixelData {
     pub level: usize,
     pub rgb: Option<(u8, u8, u8)>,
+    pub alpha: u8,
 }
 
 pub struct Txtr {
@@ -50,16 +207,8 @@ pub struct Txtr {
     pub height: usize,
 }
 
-const ANSI_RESET: &str = "\x1b[0m";
-const UPPER_HALF_BLOCK: char = '▀';
-
-fn format_color(r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[38;2;{};{};{}m", r, g, b)
-}
-
-fn format_bg_color(r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[48;2;{};{};{}m", r, g, b)
-}
+pub const UPPER_HALF_BLOCK: char = '▀';
+pub const LOWER_HALF_BLOCK: char = '▄';
 
 impl Txtr {
     pub fn new(
@@ -87,6 +236,17 @@ impl Txtr {
         })
     }
 
+    /// Swap in a new source image, e.g. the next animation frame, clearing
+    /// levels computed for the previous one
+    pub fn set_image(&mut self, img: DynamicImage) {
+        self.img = img;
+        self.pixels.clear();
+        self.max = 0;
+        self.min = usize::MAX;
+        self.width = 0;
+        self.height = 0;
+    }
+
     pub fn invert(&mut self) {
         self.img.invert();
     }
@@ -118,6 +278,11 @@ impl Txtr {
     }
 
     pub fn calc_levels(&mut self, dither: bool, char_count: usize) {
+        // Start over, so levels can be recomputed with different settings
+        self.pixels.clear();
+        self.max = 0;
+        self.min = usize::MAX;
+
         let width = self.img.width() as usize;
         let height = self.img.height() as usize;
         self.width = width;
@@ -135,6 +300,7 @@ impl Txtr {
         // First pass: collect raw levels to find min/max
         let mut raw_levels: Vec<f64> = Vec::with_capacity(pixel_count);
         let mut colors: Vec<Option<(u8, u8, u8)>> = Vec::with_capacity(pixel_count);
+        let mut alphas: Vec<u8> = Vec::with_capacity(pixel_count);
 
         for y in 0..height {
             for x in 0..width {
@@ -149,6 +315,7 @@ impl Txtr {
                 }
 
                 raw_levels.push(level as f64);
+                alphas.push(pixel[3]);
                 colors.push(if self.color_enabled {
                     Some((pixel[0], pixel[1], pixel[2]))
                 } else {
@@ -168,37 +335,18 @@ impl Txtr {
         // Apply Floyd-Steinberg dithering if enabled
         // Skip dithering when range is 0 (uniform color image) to avoid division by zero
         if dither && char_count > 1 && self.max > self.min {
+            let min = self.min as f64;
             let range = (self.max - self.min) as f64;
             let levels = char_count as f64;
 
-            for y in 0..height {
-                for x in 0..width {
-                    let idx = y * width + x;
-                    let old_val = raw_levels[idx];
-
-                    // Quantize to one of char_count levels
-                    let normalized = (old_val - self.min as f64) / range;
-                    let quantized_idx = (normalized * (levels - 1.0)).round();
-                    let new_val = self.min as f64 + (quantized_idx / (levels - 1.0)) * range;
-
-                    raw_levels[idx] = new_val;
-                    let error = old_val - new_val;
-
-                    // Distribute error to neighbors (Floyd-Steinberg pattern)
-                    if x + 1 < width {
-                        raw_levels[idx + 1] += error * 7.0 / 16.0;
-                    }
-                    if y + 1 < height {
-                        if x > 0 {
-                            raw_levels[(y + 1) * width + (x - 1)] += error * 3.0 / 16.0;
-                        }
-                        raw_levels[(y + 1) * width + x] += error * 5.0 / 16.0;
-                        if x + 1 < width {
-                            raw_levels[(y + 1) * width + (x + 1)] += error * 1.0 / 16.0;
-                        }
-                    }
-                }
-            }
+            let mut values: Vec<[f64; 1]> = raw_levels.iter().map(|&v| [v]).collect();
+            dither::floyd_steinberg(&mut values, width, height, |[old_val]| {
+                // Quantize to one of char_count levels
+                let normalized = (old_val - min) / range;
+                let quantized_idx = (normalized * (levels - 1.0)).round();
+                [min + (quantized_idx / (levels - 1.0)) * range]
+            });
+            raw_levels = values.into_iter().map(|[v]| v).collect();
         }
 
         // Build pixel data with newline markers
@@ -209,15 +357,162 @@ impl Txtr {
                 self.pixels.push(Some(PixelData {
                     level,
                     rgb: colors[idx],
+                    alpha: alphas[idx],
                 }));
             }
             self.pixels.push(None); // newline marker
         }
     }
 
-    pub fn print_by_level(&self, s: &str) {
-        let chars: Vec<char> = s.chars().collect();
-        let char_count = chars.len();
+    /// Average color of each of `count` vertical bands of the image, left to right
+    pub fn column_colors(&self, count: usize) -> Vec<Rgb> {
+        let img = self.img.to_rgb8();
+        let mut columns: Vec<Vec<Rgb>> = vec![Vec::new(); img.width() as usize];
+        for (x, _, p) in img.enumerate_pixels() {
+            columns[x as usize].push((p[0], p[1], p[2]));
+        }
+        let columns: Vec<Rgb> = columns
+            .iter()
+            .map(|colors| quantize::average(colors))
+            .collect();
+
+        // Bands narrower than a pixel take the column they fall in
+        let width = columns.len();
+        (0..count)
+            .map(|band| {
+                let start = band * width / count;
+                let end = ((band + 1) * width / count).max(start + 1).min(width);
+                quantize::average(&columns[start.min(end)..end])
+            })
+            .collect()
+    }
+
+    /// Level halfway between min and max; the natural cut-off after 2-level dithering
+    pub fn midpoint(&self) -> usize {
+        (self.min + self.max) / 2
+    }
+
+    /// Level that best splits the image into two classes: Otsu's method, which
+    /// maximizes the variance between the pixels at or below it and those above
+    pub fn otsu(&self) -> usize {
+        let mut histogram = [0usize; 256];
+        for data in self.pixels.iter().flatten() {
+            histogram[data.level.min(255)] += 1;
+        }
+        let total: usize = histogram.iter().sum();
+        let level_sum: f64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(l, &n)| (l * n) as f64)
+            .sum();
+
+        let (mut below, mut below_sum) = (0usize, 0.0);
+        let (mut best, mut best_variance) = (self.midpoint(), 0.0);
+        for (level, &count) in histogram.iter().enumerate() {
+            below += count;
+            below_sum += (level * count) as f64;
+            let above = total - below;
+            if below == 0 || above == 0 {
+                continue;
+            }
+            let mean_below = below_sum / below as f64;
+            let mean_above = (level_sum - below_sum) / above as f64;
+            let variance = (below * above) as f64 * (mean_below - mean_above).powi(2);
+            if variance > best_variance {
+                (best, best_variance) = (level, variance);
+            }
+        }
+        best
+    }
+
+    /// Row-major mask of pixels whose level is above the threshold. Adaptive
+    /// thresholds compare each pixel with the mean of a square around it
+    /// (Bradley's method), so uneven lighting doesn't swallow detail.
+    pub fn threshold_mask(&self, threshold: Threshold) -> Vec<bool> {
+        let level = match threshold {
+            Threshold::Fixed(level) => level,
+            Threshold::Otsu => self.otsu(),
+            Threshold::Adaptive => return self.adaptive_mask(),
+        };
+        self.pixels
+            .iter()
+            .flatten()
+            .map(|data| data.level > level)
+            .collect()
+    }
+
+    /// Row-major mask of the pixels that belong to the subject
+    pub fn region_mask(&self, source: &MaskSource) -> Vec<bool> {
+        match source {
+            MaskSource::Image(img) => img
+                .resize_exact(
+                    self.width as u32,
+                    self.height as u32,
+                    image::imageops::FilterType::Triangle,
+                )
+                .to_luma8()
+                .pixels()
+                .map(|p| p[0] > 127)
+                .collect(),
+            MaskSource::Alpha(threshold) => self
+                .pixels
+                .iter()
+                .flatten()
+                .map(|data| data.alpha >= *threshold)
+                .collect(),
+            MaskSource::Level(threshold) => self.threshold_mask(*threshold),
+        }
+    }
+
+    fn adaptive_mask(&self) -> Vec<bool> {
+        let (width, height) = (self.width, self.height);
+        let radius = (width.max(height) / ADAPTIVE_WINDOW).max(1);
+
+        // Summed-area table with a zero row and column in front
+        let mut sums = vec![0usize; (width + 1) * (height + 1)];
+        for y in 0..height {
+            for x in 0..width {
+                let level = self.pixel(x, y).map_or(0, |data| data.level);
+                sums[(y + 1) * (width + 1) + x + 1] =
+                    level + sums[y * (width + 1) + x + 1] + sums[(y + 1) * (width + 1) + x]
+                        - sums[y * (width + 1) + x];
+            }
+        }
+
+        let mut mask = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+            for x in 0..width {
+                let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+                let sum = sums[bottom * (width + 1) + right] + sums[top * (width + 1) + left]
+                    - sums[top * (width + 1) + right]
+                    - sums[bottom * (width + 1) + left];
+                let mean = sum as f64 / ((bottom - top) * (right - left)) as f64;
+                let level = self.pixel(x, y).map_or(0, |data| data.level);
+                mask.push(level as f64 > mean * (1.0 - ADAPTIVE_OFFSET));
+            }
+        }
+        mask
+    }
+
+    /// Pixel data at (x, y), if inside the image
+    fn pixel(&self, x: usize, y: usize) -> Option<&PixelData> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        // Row stride is width + 1 for the None newline marker
+        self.pixels.get(y * (self.width + 1) + x)?.as_ref()
+    }
+
+    /// Map each pixel to a character by brightness level
+    pub fn grid_by_level(&self, s: &str) -> Grid {
+        let shades: Vec<(char, Attr)> = s.chars().map(|c| (c, Attr::Normal)).collect();
+        self.grid_by_shade(&shades)
+    }
+
+    /// Map each pixel to a character and attribute by brightness level
+    pub fn grid_by_shade(&self, shades: &[(char, Attr)]) -> Grid {
+        let char_count = shades.len();
 
         // Avoid division by zero
         let range = if self.max > self.min {
@@ -226,271 +521,458 @@ impl Txtr {
             1
         };
 
-        // Pre-allocate output buffer: ~20 bytes per pixel with color, ~2 without
-        let bytes_per_pixel = if self.color_enabled { 25 } else { 2 };
-        let estimated_size = self.width * self.height * bytes_per_pixel;
-        let mut output = String::with_capacity(estimated_size);
-        for pixel in &self.pixels {
-            match pixel {
-                Some(data) => {
-                    let adjusted = data.level.saturating_sub(self.min);
-                    let index = (adjusted / range).min(char_count - 1);
-                    let c = chars[index];
-
-                    if let Some((r, g, b)) = data.rgb {
-                        output.push_str(&format_color(r, g, b));
-                        output.push(c);
-                    } else {
-                        output.push(c);
-                    }
-                }
-                None => {
-                    if self.color_enabled {
-                        output.push_str(ANSI_RESET);
-                    }
-                    output.push('\n');
-                }
-            }
-        }
-        if self.color_enabled {
-            output.push_str(ANSI_RESET);
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        for data in self.pixels.iter().flatten() {
+            let adjusted = data.level.saturating_sub(self.min);
+            let (glyph, attr) = shades[(adjusted / range).min(char_count - 1)];
+            grid.push(Cell {
+                glyph,
+                level: data.level,
+                fg: data.rgb,
+                bg: None,
+                attr,
+            });
         }
-
-        print!("{}", output);
+        grid
     }
 
-    pub fn print_in_order(&self, s: &str, level: usize) {
+    /// Cycle through characters in sequence for pixels above the threshold
+    pub fn grid_in_order(&self, s: &str, threshold: Threshold) -> Grid {
         let chars: Vec<char> = s.chars().collect();
+        let mask = self.threshold_mask(threshold);
         let mut count = 0;
         let chars_len = chars.len();
 
-        // Pre-allocate output buffer: ~20 bytes per pixel with color, ~2 without
-        let bytes_per_pixel = if self.color_enabled { 25 } else { 2 };
-        let estimated_size = self.width * self.height * bytes_per_pixel;
-        let mut output = String::with_capacity(estimated_size);
-        for pixel in &self.pixels {
-            match pixel {
-                Some(data) => {
-                    if count >= chars_len {
-                        count = 0;
-                    }
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        for (data, &lit) in self.pixels.iter().flatten().zip(&mask) {
+            if count >= chars_len {
+                count = 0;
+            }
 
-                    let c = chars[count];
+            if lit {
+                grid.push(Cell {
+                    glyph: chars[count],
+                    level: data.level,
+                    fg: data.rgb,
+                    bg: None,
+                    attr: Attr::Normal,
+                });
+                count += 1;
+            } else {
+                grid.push(Cell {
+                    level: data.level,
+                    ..Cell::blank()
+                });
+            }
+        }
+        grid
+    }
 
-                    if data.level > level {
-                        count += 1;
-                        if let Some((r, g, b)) = data.rgb {
-                            output.push_str(&format_color(r, g, b));
-                            output.push(c);
-                        } else {
-                            output.push(c);
-                        }
-                    } else {
-                        output.push(' ');
-                    }
+    /// One glyph for pixels above the threshold and another for the rest
+    pub fn grid_two_tone(&self, lit_glyph: char, unlit_glyph: char, threshold: Threshold) -> Grid {
+        let mask = self.threshold_mask(threshold);
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        for (data, &lit) in self.pixels.iter().flatten().zip(&mask) {
+            grid.push(Cell {
+                glyph: if lit { lit_glyph } else { unlit_glyph },
+                level: data.level,
+                fg: data.rgb,
+                bg: None,
+                attr: Attr::Normal,
+            });
+        }
+        grid
+    }
+
+    /// Flow text through the pixels above the threshold in reading order,
+    /// starting over when it runs out. With keep_words, a word that doesn't fit
+    /// in the rest of a run of lit pixels moves to the next run, and runs don't
+    /// start with spaces; words too long for a whole run are broken.
+    pub fn grid_text(&self, text: &[char], threshold: Threshold, keep_words: bool) -> Grid {
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        if text.is_empty() {
+            return grid;
+        }
+        let mask = self.threshold_mask(threshold);
+        let mut next = 0;
+        let word_len = |start: usize| {
+            (0..text.len())
+                .take_while(|&i| text[(start + i) % text.len()] != ' ')
+                .count()
+        };
+
+        for y in 0..self.height {
+            let mut x = 0;
+            while x < self.width {
+                let lit = |x: usize| mask[y * self.width + x];
+                if !lit(x) {
+                    grid.push(self.text_cell(x, y, None));
+                    x += 1;
+                    continue;
                 }
-                None => {
-                    if self.color_enabled {
-                        output.push_str(ANSI_RESET);
+
+                let run_end = (x..self.width).find(|&x| !lit(x)).unwrap_or(self.width);
+                let run_start = x;
+                while x < run_end {
+                    if keep_words {
+                        // Runs start at a word, ideally one that fits
+                        while x == run_start && text[next] == ' ' {
+                            next = (next + 1) % text.len();
+                        }
+                        let starts_word =
+                            text[next] != ' ' && text[(next + text.len() - 1) % text.len()] == ' ';
+                        if x > run_start && starts_word && word_len(next) > run_end - x {
+                            while x < run_end {
+                                grid.push(self.text_cell(x, y, None));
+                                x += 1;
+                            }
+                            break;
+                        }
                     }
-                    output.push('\n');
+                    grid.push(self.text_cell(x, y, Some(text[next])));
+                    next = (next + 1) % text.len();
+                    x += 1;
                 }
             }
         }
-        if self.color_enabled {
-            output.push_str(ANSI_RESET);
+        grid
+    }
+
+    fn text_cell(&self, x: usize, y: usize, glyph: Option<char>) -> Cell {
+        let data = self.pixel(x, y);
+        let level = data.map_or(0, |data| data.level);
+        match glyph {
+            Some(glyph) => Cell {
+                glyph,
+                level,
+                fg: data.and_then(|data| data.rgb),
+                ..Cell::blank()
+            },
+            None => Cell {
+                level,
+                ..Cell::blank()
+            },
         }
+    }
 
-        print!("{}", output);
+    /// Map each pixel to the emoji nearest its color. Emoji take two columns,
+    /// and the cells carry no ANSI colors of their own.
+    pub fn grid_emoji(&self) -> Grid {
+        let mut grid = Grid::new(self.width, self.height, false);
+        for data in self.pixels.iter().flatten() {
+            grid.push(Cell {
+                glyph: emoji::nearest(data.rgb.unwrap_or((0, 0, 0))),
+                level: data.level,
+                ..Cell::blank()
+            });
+        }
+        grid
     }
 
-    /// Print using Unicode half-block characters for 2x vertical resolution.
+    /// Use Unicode half-block characters for 2x vertical resolution.
     /// Each character cell represents 2 vertical pixels using foreground/background colors.
-    /// Uses self.pixels to respect dithering and other processing.
-    pub fn print_blocks(&self) {
-        let width = self.width;
-        let height = self.height;
-        // Pre-allocate: ~25 bytes per cell (fg + bg escape codes + char)
-        let estimated_size = width * height.div_ceil(2) * 30;
-        let mut output = String::with_capacity(estimated_size);
-
-        // Row stride in pixels array is width + 1 (for the None newline marker)
-        let row_stride = width + 1;
+    /// Uses self.pixels to respect dithering and other processing. Pixels with
+    /// alpha below the threshold are left to the terminal's own background.
+    pub fn grid_blocks(&self, alpha_threshold: u8) -> Grid {
+        let mut grid = Grid::new(self.width, self.height.div_ceil(2), self.color_enabled);
+        let color = |data: Option<&PixelData>| data.and_then(|d| d.rgb).unwrap_or((0, 0, 0));
+        let opaque = |data: Option<&PixelData>| data.is_some_and(|d| d.alpha >= alpha_threshold);
 
         // Process 2 rows at a time
-        let mut y = 0usize;
-        while y < height {
-            for x in 0..width {
-                let top_idx = y * row_stride + x;
-                let (top_r, top_g, top_b) = if let Some(Some(data)) = self.pixels.get(top_idx) {
-                    data.rgb.unwrap_or((0, 0, 0))
-                } else {
-                    (0, 0, 0)
-                };
-
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let top = self.pixel(x, y);
                 // Bottom pixel: use next row if available, otherwise same as top
-                let (bot_r, bot_g, bot_b) = if y + 1 < height {
-                    let bot_idx = (y + 1) * row_stride + x;
-                    if let Some(Some(data)) = self.pixels.get(bot_idx) {
-                        data.rgb.unwrap_or((0, 0, 0))
-                    } else {
-                        (top_r, top_g, top_b)
-                    }
-                } else {
-                    (top_r, top_g, top_b)
+                let bottom = self.pixel(x, y + 1).or(top);
+
+                // Top pixel = foreground, bottom pixel = background; a lone
+                // opaque bottom pixel is drawn as the foreground of a lower block
+                let (glyph, fg, bg) = match (opaque(top), opaque(bottom)) {
+                    (true, true) => (UPPER_HALF_BLOCK, Some(color(top)), Some(color(bottom))),
+                    (true, false) => (UPPER_HALF_BLOCK, Some(color(top)), None),
+                    (false, true) => (LOWER_HALF_BLOCK, Some(color(bottom)), None),
+                    (false, false) => (' ', None, None),
                 };
 
                 // BUG C2 fix: Only output ANSI codes when color is enabled
-                if self.color_enabled {
-                    // Top pixel = foreground, Bottom pixel = background
-                    output.push_str(&format_color(top_r, top_g, top_b));
-                    output.push_str(&format_bg_color(bot_r, bot_g, bot_b));
-                }
-                output.push(UPPER_HALF_BLOCK);
-            }
-            if self.color_enabled {
-                output.push_str(ANSI_RESET);
+                grid.push(Cell {
+                    glyph,
+                    level: top.map_or(0, |data| data.level),
+                    fg: fg.filter(|_| self.color_enabled),
+                    bg: bg.filter(|_| self.color_enabled),
+                    attr: Attr::Normal,
+                });
             }
-            output.push('\n');
-            y += 2;
         }
-
-        print!("{}", output);
+        grid
     }
 
-    /// Generate output to a String instead of printing (for testing)
-    #[cfg(test)]
-    pub fn render_by_level(&self, s: &str) -> String {
-        let chars: Vec<char> = s.chars().collect();
-        let char_count = chars.len();
-
-        let range = if self.max > self.min {
-            (self.max - self.min) / char_count + 1
-        } else {
-            1
-        };
-
-        let bytes_per_pixel = if self.color_enabled { 25 } else { 2 };
-        let estimated_size = self.width * self.height * bytes_per_pixel;
-        let mut output = String::with_capacity(estimated_size);
-        for pixel in &self.pixels {
-            match pixel {
-                Some(data) => {
-                    let adjusted = data.level.saturating_sub(self.min);
-                    let index = (adjusted / range).min(char_count - 1);
-                    let c = chars[index];
-
-                    if let Some((r, g, b)) = data.rgb {
-                        output.push_str(&format_color(r, g, b));
-                        output.push(c);
-                    } else {
-                        output.push(c);
+    /// Pack 2x4 pixel blocks into Braille patterns, raising dots brighter than
+    /// the threshold. With color, each cell takes the average color of its raised dots.
+    pub fn grid_braille(&self, threshold: Threshold) -> Grid {
+        let mask = self.threshold_mask(threshold);
+        let cols = self.width.div_ceil(2);
+        let rows = self.height.div_ceil(4);
+        let mut grid = Grid::new(cols, rows, self.color_enabled);
+
+        for cy in 0..rows {
+            for cx in 0..cols {
+                let mut pattern = 0u8;
+                let mut lit_colors = Vec::new();
+                let (mut level_sum, mut count) = (0, 0);
+                for dy in 0..4 {
+                    for dx in 0..2 {
+                        let (x, y) = (cx * 2 + dx, cy * 4 + dy);
+                        let Some(data) = self.pixel(x, y) else {
+                            continue;
+                        };
+                        level_sum += data.level;
+                        count += 1;
+                        if mask[y * self.width + x] {
+                            pattern |= braille::dot_bit(dx, dy);
+                            lit_colors.extend(data.rgb);
+                        }
                     }
                 }
-                None => {
-                    if self.color_enabled {
-                        output.push_str(ANSI_RESET);
+                grid.push(Cell {
+                    glyph: braille::to_unicode(pattern),
+                    level: level_sum / count.max(1),
+                    fg: (!lit_colors.is_empty()).then(|| quantize::average(&lit_colors)),
+                    bg: None,
+                    attr: Attr::Normal,
+                });
+            }
+        }
+        grid
+    }
+
+    /// Split each cell into mosaic sub-pixels, cluster their colors into the
+    /// two that represent them best, and pick the glyph covering the foreground ones
+    pub fn grid_mosaic(&self, mosaic: Mosaic) -> Grid {
+        let (cell_w, cell_h) = mosaic.cell_size();
+        let cols = self.width.div_ceil(cell_w);
+        let rows = self.height.div_ceil(cell_h);
+        let mut grid = Grid::new(cols, rows, self.color_enabled);
+
+        for cy in 0..rows {
+            for cx in 0..cols {
+                let mut bits = Vec::with_capacity(cell_w * cell_h);
+                let mut colors = Vec::with_capacity(cell_w * cell_h);
+                let mut level_sum = 0;
+                for dy in 0..cell_h {
+                    for dx in 0..cell_w {
+                        if let Some(data) = self.pixel(cx * cell_w + dx, cy * cell_h + dy) {
+                            bits.push(dy * cell_w + dx);
+                            colors.push(data.rgb.unwrap_or((0, 0, 0)));
+                            level_sum += data.level;
+                        }
                     }
-                    output.push('\n');
                 }
+
+                let (members, fg, bg) = quantize::split_two(&colors);
+                let pattern = bits
+                    .iter()
+                    .zip(&members)
+                    .filter(|(_, &member)| member)
+                    .fold(0u8, |pattern, (&bit, _)| pattern | 1 << bit);
+                grid.push(Cell {
+                    glyph: mosaic.glyph(pattern),
+                    level: level_sum / bits.len().max(1),
+                    fg: self.color_enabled.then_some(fg),
+                    bg: self.color_enabled.then_some(bg),
+                    attr: Attr::Normal,
+                });
             }
         }
-        if self.color_enabled {
-            output.push_str(ANSI_RESET);
+        grid
+    }
+
+    /// Give the cells of a grid with one cell per pixel a background from the
+    /// pixel's color
+    pub fn fill_background(&self, grid: &mut Grid, background: Background) {
+        for (cell, data) in grid.cells.iter_mut().zip(self.pixels.iter().flatten()) {
+            let Some((r, g, b)) = data.rgb else {
+                continue;
+            };
+            match background {
+                Background::None => {}
+                Background::Dim => {
+                    let dim = |c: u8| (c as f64 * BACKGROUND_DIM).round() as u8;
+                    cell.bg = Some((dim(r), dim(g), dim(b)));
+                }
+                Background::Only => {
+                    *cell = Cell {
+                        level: data.level,
+                        bg: Some((r, g, b)),
+                        ..Cell::blank()
+                    };
+                }
+            }
         }
-        output
     }
 
-    /// Generate output to a String instead of printing (for testing)
-    #[cfg(test)]
-    pub fn render_in_order(&self, s: &str, level: usize) -> String {
-        let chars: Vec<char> = s.chars().collect();
-        let mut count = 0;
-        let chars_len = chars.len();
+    /// Line characters for pixels on edges stronger than the threshold, row-major
+    pub fn edge_glyphs(&self, threshold: usize) -> Vec<Option<char>> {
+        let levels: Vec<usize> = self
+            .pixels
+            .iter()
+            .flatten()
+            .map(|data| data.level)
+            .collect();
+        edges::glyphs(&levels, self.width, self.height, threshold)
+    }
 
-        let bytes_per_pixel = if self.color_enabled { 25 } else { 2 };
-        let estimated_size = self.width * self.height * bytes_per_pixel;
-        let mut output = String::with_capacity(estimated_size);
-        for pixel in &self.pixels {
-            match pixel {
-                Some(data) => {
-                    if count >= chars_len {
-                        count = 0;
-                    }
+    /// Draw edge glyphs over a grid with one cell per pixel, in the given
+    /// color or else the pixel's own
+    pub fn overlay_edges(&self, grid: &mut Grid, glyphs: &[Option<char>], color: Option<Rgb>) {
+        let cells = grid.cells.iter_mut().zip(self.pixels.iter().flatten());
+        for ((cell, data), glyph) in cells.zip(glyphs) {
+            if let Some(glyph) = glyph {
+                cell.glyph = *glyph;
+                cell.fg = color.or(data.rgb);
+            }
+        }
+        grid.color |= color.is_some();
+    }
+
+    /// Draw edges stronger than the threshold with characters that follow
+    /// their direction, leaving flat areas blank
+    pub fn grid_edges(&self, threshold: usize, color: Option<Rgb>) -> Grid {
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        for data in self.pixels.iter().flatten() {
+            grid.push(Cell {
+                level: data.level,
+                ..Cell::blank()
+            });
+        }
+        self.overlay_edges(&mut grid, &self.edge_glyphs(threshold), color);
+        grid
+    }
 
-                    let c = chars[count];
+    /// Trace edges stronger than the threshold as box-drawing lines
+    pub fn grid_lines(&self, threshold: usize, style: LineStyle, color: Option<Rgb>) -> Grid {
+        let levels: Vec<usize> = self
+            .pixels
+            .iter()
+            .flatten()
+            .map(|data| data.level)
+            .collect();
+        let glyphs = lines::glyphs(&levels, self.width, self.height, threshold, style);
+        let mut grid = Grid::new(self.width, self.height, self.color_enabled);
+        for data in self.pixels.iter().flatten() {
+            grid.push(Cell {
+                level: data.level,
+                ..Cell::blank()
+            });
+        }
+        self.overlay_edges(&mut grid, &glyphs, color);
+        grid
+    }
 
-                    if data.level > level {
-                        count += 1;
-                        if let Some((r, g, b)) = data.rgb {
-                            output.push_str(&format_color(r, g, b));
-                            output.push(c);
-                        } else {
-                            output.push(c);
+    /// Compare each 8x13 cell of pixels against the glyph bitmaps and keep the
+    /// glyph with the least squared error. With color, every glyph is scored
+    /// with the fg/bg pair that fits it best (the means of the pixels it covers
+    /// and leaves out); without, glyphs draw at the brightest level on the darkest.
+    pub fn grid_shape(&self, glyphs: &[(char, Bitmap)]) -> Grid {
+        let (cell_w, cell_h) = (GLYPH_WIDTH as usize, GLYPH_HEIGHT as usize);
+        let cols = self.width.div_ceil(cell_w);
+        let rows = self.height.div_ceil(cell_h);
+        let mut grid = Grid::new(cols, rows, self.color_enabled);
+        let (dark, bright) = (self.min as f64, self.max as f64);
+
+        for cy in 0..rows {
+            for cx in 0..cols {
+                // Without color only the level channel is compared
+                let mut samples = Vec::with_capacity(cell_w * cell_h);
+                let mut level_sum = 0;
+                for dy in 0..cell_h {
+                    for dx in 0..cell_w {
+                        if let Some(data) = self.pixel(cx * cell_w + dx, cy * cell_h + dy) {
+                            let value = match data.rgb.filter(|_| self.color_enabled) {
+                                Some((r, g, b)) => [r as f64, g as f64, b as f64],
+                                None => [data.level as f64, 0.0, 0.0],
+                            };
+                            samples.push((dx, dy, value));
+                            level_sum += data.level;
                         }
-                    } else {
-                        output.push(' ');
                     }
                 }
-                None => {
-                    if self.color_enabled {
-                        output.push_str(ANSI_RESET);
+
+                let count = samples.len() as f64;
+                let total = samples.iter().fold([0.0; 3], |sum, (_, _, v)| add(sum, *v));
+                let sum_sq: f64 = samples.iter().map(|(_, _, v)| dot(*v, *v)).sum();
+
+                let mut best = (f64::INFINITY, ' ', [0.0; 3], [0.0; 3]);
+                for (glyph, bitmap) in glyphs {
+                    let (mut on, mut on_count) = ([0.0; 3], 0.0);
+                    for &(dx, dy, value) in &samples {
+                        if font::lit(bitmap, dx, dy) {
+                            on = add(on, value);
+                            on_count += 1.0;
+                        }
+                    }
+                    let off = std::array::from_fn(|i| total[i] - on[i]);
+                    let off_count = count - on_count;
+
+                    let (error, fg, bg) = if self.color_enabled {
+                        let fg = on.map(|c| c / on_count.max(1.0));
+                        let bg = off.map(|c| c / off_count.max(1.0));
+                        (sum_sq - dot(on, fg) - dot(off, bg), fg, bg)
+                    } else {
+                        // Sum of (level - bright)^2 over covered pixels and
+                        // (level - dark)^2 over the rest
+                        let error = sum_sq - 2.0 * (bright * on[0] + dark * off[0])
+                            + on_count * bright * bright
+                            + off_count * dark * dark;
+                        (error, [bright; 3], [dark; 3])
+                    };
+                    if error < best.0 {
+                        // Blank glyphs have no pixels of their own to color
+                        let fg = if on_count == 0.0 { bg } else { fg };
+                        best = (error, *glyph, fg, bg);
                     }
-                    output.push('\n');
                 }
+
+                let (_, glyph, fg, bg) = best;
+                let rgb =
+                    |c: [f64; 3]| (c[0].round() as u8, c[1].round() as u8, c[2].round() as u8);
+                grid.push(Cell {
+                    glyph,
+                    level: level_sum / samples.len().max(1),
+                    fg: self.color_enabled.then(|| rgb(fg)),
+                    bg: self.color_enabled.then(|| rgb(bg)),
+                    attr: Attr::Normal,
+                });
             }
         }
-        if self.color_enabled {
-            output.push_str(ANSI_RESET);
-        }
-        output
+        grid
     }
 
     /// Generate output to a String instead of printing (for testing)
     #[cfg(test)]
-    pub fn render_blocks(&self) -> String {
-        let width = self.width;
-        let height = self.height;
-        let estimated_size = width * height.div_ceil(2) * 30;
-        let mut output = String::with_capacity(estimated_size);
+    pub fn render_by_level(&self, s: &str) -> String {
+        self.grid_by_level(s).to_ansi()
+    }
 
-        let row_stride = width + 1;
+    /// Generate output to a String instead of printing (for testing)
+    #[cfg(test)]
+    pub fn render_in_order(&self, s: &str, level: usize) -> String {
+        self.grid_in_order(s, Threshold::Fixed(level)).to_ansi()
+    }
 
-        let mut y = 0usize;
-        while y < height {
-            for x in 0..width {
-                let top_idx = y * row_stride + x;
-                let (top_r, top_g, top_b) = if let Some(Some(data)) = self.pixels.get(top_idx) {
-                    data.rgb.unwrap_or((0, 0, 0))
-                } else {
-                    (0, 0, 0)
-                };
+    /// Generate output to a String instead of printing (for testing)
+    #[cfg(test)]
+    pub fn render_blocks(&self) -> String {
+        self.grid_blocks(0).to_ansi()
+    }
+}
 
-                let (bot_r, bot_g, bot_b) = if y + 1 < height {
-                    let bot_idx = (y + 1) * row_stride + x;
-                    if let Some(Some(data)) = self.pixels.get(bot_idx) {
-                        data.rgb.unwrap_or((0, 0, 0))
-                    } else {
-                        (top_r, top_g, top_b)
-                    }
-                } else {
-                    (top_r, top_g, top_b)
-                };
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
 
-                if self.color_enabled {
-                    output.push_str(&format_color(top_r, top_g, top_b));
-                    output.push_str(&format_bg_color(bot_r, bot_g, bot_b));
-                }
-                output.push(UPPER_HALF_BLOCK);
-            }
-            if self.color_enabled {
-                output.push_str(ANSI_RESET);
-            }
-            output.push('\n');
-            y += 2;
-        }
-        output
-    }
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a.iter().zip(&b).map(|(x, y)| x * y).sum()
 }
 
 #[cfg(test)]
@@ -631,7 +1113,7 @@ mod tests {
         txtr.calc_levels(false, 10);
         assert_eq!(txtr.min, 0);
         // Max is 254 or 255 due to floating point precision in luma709
-        assert!(txtr.max >= 254 && txtr.max <= 255);
+        assert!((254..=255).contains(&txtr.max));
     }
 
     // print_by_level() tests
@@ -767,9 +1249,419 @@ mod tests {
         assert!(RAMP_SIMPLE.chars().count() >= 2);
     }
 
+    // threshold_mask() tests
+    #[test]
+    fn threshold_mask_marks_bright_pixels() {
+        let pixels = vec![(255, 255, 255), (0, 0, 0), (200, 200, 200)];
+        let mut txtr = create_test_txtr_rgb(3, 1, &pixels, false);
+        txtr.calc_levels(false, 2);
+        assert_eq!(
+            txtr.threshold_mask(Threshold::Fixed(127)),
+            vec![true, false, true]
+        );
+    }
+
+    #[test]
+    fn otsu_splits_between_clusters() {
+        // Dark pixels around 40, bright ones around 180
+        let levels = [30, 40, 50, 170, 180, 190];
+        let pixels: Vec<Rgb> = levels.iter().map(|&l| (l, l, l)).collect();
+        let mut txtr = create_test_txtr_rgb(6, 1, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let level = txtr.otsu();
+        assert!((50..170).contains(&level), "{}", level);
+        assert_eq!(
+            txtr.threshold_mask(Threshold::Otsu),
+            vec![false, false, false, true, true, true]
+        );
+
+        // A dark image where 127 would light nothing
+        let pixels: Vec<Rgb> = [10, 12, 60, 64].iter().map(|&l| (l, l, l)).collect();
+        let mut txtr = create_test_txtr_rgb(4, 1, &pixels, false);
+        txtr.calc_levels(false, 2);
+        assert_eq!(
+            txtr.threshold_mask(Threshold::Otsu),
+            vec![false, false, true, true]
+        );
+    }
+
+    #[test]
+    fn otsu_uniform_image() {
+        let mut txtr = create_test_txtr(3, 3, 90, false);
+        txtr.calc_levels(false, 2);
+        assert!(txtr.threshold_mask(Threshold::Otsu).iter().all(|&lit| !lit));
+    }
+
+    #[test]
+    fn adaptive_threshold_follows_local_brightness() {
+        // A bright stroke on a dark left half and a brighter stroke on a
+        // bright right half: no single level picks out both
+        let pixels: Vec<Rgb> = (0..32)
+            .map(|i| {
+                let (x, y) = (i % 16, i / 16);
+                let l = match (x < 8, y == 0 && x % 8 == 3) {
+                    (true, false) => 20,
+                    (true, true) => 120,
+                    (false, false) => 180,
+                    (false, true) => 250,
+                };
+                (l, l, l)
+            })
+            .collect();
+        let mut txtr = create_test_txtr_rgb(16, 2, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let mask = txtr.threshold_mask(Threshold::Adaptive);
+        assert!(mask[3] && mask[11]);
+        // The stroke stands out from the dark background around it
+        assert!(!mask[2] && !mask[4]);
+    }
+
+    #[test]
+    fn region_mask_sources() {
+        let pixels = vec![(255, 255, 255), (0, 0, 0), (200, 200, 200), (10, 10, 10)];
+        let mut txtr = create_test_txtr_rgb(4, 1, &pixels, false);
+        if let DynamicImage::ImageRgba8(img) = &mut txtr.img {
+            img.get_pixel_mut(2, 0)[3] = 0;
+        }
+        txtr.calc_levels(false, 2);
+
+        let level = MaskSource::Level(Threshold::Fixed(127));
+        assert_eq!(txtr.region_mask(&level), vec![true, false, true, false]);
+        assert_eq!(
+            txtr.region_mask(&MaskSource::Alpha(128)),
+            vec![true, true, false, true]
+        );
+
+        // A mask image at another size is scaled to the output
+        let mask =
+            image::GrayImage::from_fn(8, 2, |x, _| image::Luma([if x < 4 { 0 } else { 255 }]));
+        let source = MaskSource::Image(DynamicImage::ImageLuma8(mask));
+        assert_eq!(txtr.region_mask(&source), vec![false, false, true, true]);
+    }
+
+    #[test]
+    fn column_colors_average_bands() {
+        let pixels = vec![(200, 0, 0), (100, 0, 0), (0, 0, 60), (0, 0, 20)];
+        let txtr = create_test_txtr_rgb(4, 1, &pixels, false);
+        assert_eq!(txtr.column_colors(2), vec![(150, 0, 0), (0, 0, 40)]);
+        // More bands than columns repeat the columns
+        assert_eq!(txtr.column_colors(8)[1], (200, 0, 0));
+        assert_eq!(txtr.column_colors(8)[7], (0, 0, 20));
+    }
+
+    #[test]
+    fn grid_two_tone_glyphs() {
+        let pixels = vec![(255, 255, 255), (0, 0, 0), (200, 200, 200)];
+        let mut txtr = create_test_txtr_rgb(3, 1, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_two_tone('#', '.', Threshold::Fixed(127));
+        assert_eq!(grid.to_ansi(), "#.#\n");
+    }
+
+    #[test]
+    fn midpoint_after_two_level_dither() {
+        let pixels = vec![(255, 255, 255), (0, 0, 0), (128, 128, 128), (64, 64, 64)];
+        let mut txtr = create_test_txtr_rgb(2, 2, &pixels, false);
+        txtr.calc_levels(true, 2);
+        let mid = txtr.midpoint();
+        // Dithered levels collapse to min or max, so none sit on the midpoint
+        assert!(txtr.pixels.iter().flatten().all(|p| p.level != mid));
+    }
+
     // DEFAULT_CHARS test
     #[test]
     fn default_chars_is_valid() {
         assert!(DEFAULT_CHARS.chars().count() >= 2);
     }
+
+    #[test]
+    fn set_image_resets_levels() {
+        let mut txtr = create_test_txtr(2, 2, 200, false);
+        txtr.calc_levels(false, 2);
+        let next = create_test_txtr(3, 1, 50, false).img;
+        txtr.set_image(next);
+        txtr.calc_levels(false, 2);
+        assert_eq!((txtr.width, txtr.height), (3, 1));
+        assert_eq!(txtr.pixels.len(), 4); // 3 pixels + newline marker
+        assert_eq!((txtr.min, txtr.max), (50, 50));
+    }
+
+    #[test]
+    fn grid_braille_packs_dots_and_averages_lit_colors() {
+        // 2x4 block: left column bright red/blue, right column dark
+        #[rustfmt::skip]
+        let pixels = [
+            (255, 0, 0), (0, 0, 0),
+            (0, 0, 255), (0, 0, 0),
+            (255, 0, 255), (0, 0, 0),
+            (0, 0, 255), (0, 0, 0),
+        ];
+        let mut txtr = create_test_txtr_rgb(2, 4, &pixels, true);
+        txtr.channel = encoder::select("red");
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_braille(Threshold::Fixed(100));
+        assert_eq!((grid.width, grid.height), (1, 1));
+        // Only the dots with red (1, 3) are above the threshold
+        assert_eq!(grid.cells[0].glyph, '⠅');
+        assert_eq!(grid.cells[0].fg, Some((255, 0, 127)));
+    }
+
+    #[test]
+    fn grid_braille_partial_cells() {
+        let mut txtr = create_test_txtr(3, 5, 255, false);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_braille(Threshold::Fixed(127));
+        assert_eq!((grid.width, grid.height), (2, 2));
+        assert_eq!(grid.cells[0].glyph, '⣿');
+        assert_eq!(grid.cells[1].glyph, '⡇'); // one column of four dots
+        assert_eq!(grid.cells[3].glyph, '⠁');
+        assert_eq!(grid.cells[0].fg, None);
+    }
+
+    #[test]
+    fn grid_mosaic_quadrants_pick_two_colors() {
+        // Red diagonal on blue: top-left and bottom-right are red
+        #[rustfmt::skip]
+        let pixels = [
+            (255, 0, 0), (0, 0, 200),
+            (0, 0, 200), (250, 0, 0),
+        ];
+        let mut txtr = create_test_txtr_rgb(2, 2, &pixels, true);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_mosaic(Mosaic::Quadrant);
+        assert_eq!((grid.width, grid.height), (1, 1));
+        assert_eq!(grid.cells[0].glyph, '▚');
+        assert_eq!(grid.cells[0].fg, Some((252, 0, 0)));
+        assert_eq!(grid.cells[0].bg, Some((0, 0, 200)));
+    }
+
+    #[test]
+    fn grid_mosaic_sextant_cell_size() {
+        let mut txtr = create_test_txtr(4, 7, 128, true);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_mosaic(Mosaic::Sextant);
+        assert_eq!((grid.width, grid.height), (2, 3));
+        // Uniform cells have nothing to split
+        assert_eq!(grid.cells[0].glyph, ' ');
+        assert_eq!(grid.cells[0].bg, Some((128, 128, 128)));
+    }
+
+    #[test]
+    fn select_match_modes() {
+        assert_eq!(select_match("shape"), Match::Shape);
+        assert_eq!(select_match("outline"), Match::Level);
+    }
+
+    #[test]
+    fn grid_shape_picks_best_glyph_and_colors() {
+        // One 8x13 cell, left half red and right half blue
+        let pixels: Vec<_> = (0..8 * 13)
+            .map(|i| if i % 8 < 4 { (200, 0, 0) } else { (0, 0, 200) })
+            .collect();
+        let mut txtr = create_test_txtr_rgb(8, 13, &pixels, true);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_shape(&font::glyph_set("ascii,blocks"));
+        assert_eq!((grid.width, grid.height), (1, 1));
+        assert_eq!(grid.cells[0].glyph, '▌');
+        assert_eq!(grid.cells[0].fg, Some((200, 0, 0)));
+        assert_eq!(grid.cells[0].bg, Some((0, 0, 200)));
+    }
+
+    #[test]
+    fn grid_shape_without_color_draws_bright_on_dark() {
+        // Bright top half over a dark bottom half, plus a partial second cell
+        let pixels: Vec<_> = (0..10 * 13)
+            .map(|i| {
+                if i / 10 < 6 {
+                    (255, 255, 255)
+                } else {
+                    (0, 0, 0)
+                }
+            })
+            .collect();
+        let mut txtr = create_test_txtr_rgb(10, 13, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_shape(&font::glyph_set("ascii,blocks"));
+        assert_eq!((grid.width, grid.height), (2, 1));
+        assert_eq!(grid.cells[0].glyph, '▀');
+        assert_eq!(grid.cells[0].fg, None);
+    }
+
+    #[test]
+    fn grid_edges_outlines_steps() {
+        // Dark left half, bright right half
+        let pixels: Vec<_> = (0..24)
+            .map(|i| {
+                if i % 6 < 3 {
+                    (0, 0, 0)
+                } else {
+                    (255, 255, 255)
+                }
+            })
+            .collect();
+        let mut txtr = create_test_txtr_rgb(6, 4, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let output = txtr.grid_edges(64, None).to_ansi();
+        assert_eq!(output, "  |   \n".repeat(4));
+    }
+
+    #[test]
+    fn overlay_edges_keeps_fill() {
+        let pixels: Vec<_> = (0..24)
+            .map(|i| {
+                if i % 6 < 3 {
+                    (0, 0, 0)
+                } else {
+                    (255, 255, 255)
+                }
+            })
+            .collect();
+        let mut txtr = create_test_txtr_rgb(6, 4, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let edges = txtr.edge_glyphs(64);
+        let mut grid = txtr.grid_by_level(" #");
+        txtr.overlay_edges(&mut grid, &edges, Some((255, 0, 0)));
+        assert!(grid.color);
+        let row: String = grid.cells[..6].iter().map(|cell| cell.glyph).collect();
+        assert_eq!(row, "  |###");
+        assert_eq!(grid.cells[2].fg, Some((255, 0, 0)));
+        assert_eq!(grid.cells[3].fg, None);
+    }
+
+    #[test]
+    fn select_backgrounds() {
+        assert_eq!(select_background("dim"), Background::Dim);
+        assert_eq!(select_background("only"), Background::Only);
+        assert_eq!(select_background("average"), Background::None);
+    }
+
+    #[test]
+    fn fill_background_dims_or_replaces_glyphs() {
+        let pixels = [(200, 100, 0), (0, 0, 0)];
+        let mut txtr = create_test_txtr_rgb(2, 1, &pixels, true);
+        txtr.calc_levels(false, 2);
+
+        let mut grid = txtr.grid_in_order("ab", Threshold::Fixed(10));
+        txtr.fill_background(&mut grid, Background::Dim);
+        assert_eq!(grid.cells[0].glyph, 'a');
+        assert_eq!(grid.cells[0].fg, Some((200, 100, 0)));
+        assert_eq!(grid.cells[0].bg, Some((80, 40, 0)));
+        // Cells without a glyph still get the background
+        assert_eq!(grid.cells[1].bg, Some((0, 0, 0)));
+
+        let mut grid = txtr.grid_in_order("ab", Threshold::Fixed(10));
+        txtr.fill_background(&mut grid, Background::Only);
+        assert_eq!(grid.cells[0].glyph, ' ');
+        assert_eq!(grid.cells[0].fg, None);
+        assert_eq!(grid.cells[0].bg, Some((200, 100, 0)));
+    }
+
+    #[test]
+    fn attribute_ramp_orders_shades() {
+        let ramp = attribute_ramp(" .#");
+        assert_eq!(ramp[0], (' ', Attr::Normal));
+        // A reversed space is a solid block, brighter than any glyph
+        assert_eq!(ramp[ramp.len() - 1], (' ', Attr::Reverse));
+        assert!(ramp.contains(&('#', Attr::Bold)));
+        assert!(ramp.contains(&('.', Attr::Dim)));
+        // Dim, normal and bold spaces all look blank
+        assert!(!ramp.contains(&(' ', Attr::Bold)));
+
+        // Dense-to-sparse ramps stay that way round
+        let ramp = attribute_ramp("#. ");
+        assert_eq!(ramp[0], (' ', Attr::Reverse));
+    }
+
+    #[test]
+    fn grid_by_shade_sets_attributes() {
+        let mut txtr = create_test_txtr_rgb(2, 1, &[(0, 0, 0), (255, 255, 255)], false);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_by_shade(&[(' ', Attr::Normal), ('#', Attr::Bold)]);
+        assert_eq!(grid.cells[1].glyph, '#');
+        assert_eq!(grid.cells[1].attr, Attr::Bold);
+    }
+
+    #[test]
+    fn flowing_text_whitespace() {
+        let text = "Four score\n\tand  seven\x07";
+        assert_eq!(
+            flowing_text(text, false).iter().collect::<String>(),
+            "Four score  and  seven"
+        );
+        assert_eq!(
+            flowing_text(text, true).iter().collect::<String>(),
+            "Four score and seven"
+        );
+        assert!(flowing_text(" \n ", true).is_empty());
+    }
+
+    #[test]
+    fn grid_text_flows_through_lit_cells() {
+        // Two lit runs of 4 and 2 cells, split by a dark pixel
+        let bright = (255, 255, 255);
+        let pixels = [bright, bright, bright, bright, (0, 0, 0), bright, bright];
+        let mut txtr = create_test_txtr_rgb(7, 1, &pixels, false);
+        txtr.calc_levels(false, 2);
+        let text: Vec<char> = "ab cde".chars().collect();
+
+        let row = |grid: Grid| grid.cells.iter().map(|cell| cell.glyph).collect::<String>();
+        assert_eq!(
+            row(txtr.grid_text(&text, Threshold::Fixed(127), false)),
+            "ab c de"
+        );
+        // "cde" doesn't fit after "ab ", so it moves on, and the next run
+        // starts at a word
+        assert_eq!(
+            row(txtr.grid_text(&text, Threshold::Fixed(127), true)),
+            "ab   cd"
+        );
+    }
+
+    #[test]
+    fn grid_emoji_matches_colors() {
+        let pixels = [(250, 250, 250), (0, 0, 0), (60, 140, 200)];
+        let mut txtr = create_test_txtr_rgb(3, 1, &pixels, true);
+        txtr.calc_levels(false, 2);
+        let grid = txtr.grid_emoji();
+        assert!(!grid.color);
+        assert_eq!(grid.to_ansi(), "⬜⬛🌊\n");
+    }
+
+    #[test]
+    fn grid_blocks_leaves_transparent_pixels_to_terminal() {
+        // Columns: opaque over opaque, opaque over clear, clear over opaque, clear over clear
+        let mut img = RgbaImage::new(4, 2);
+        let alphas = [255, 255, 255, 0, 0, 255, 0, 0];
+        for (i, pixel) in img.pixels_mut().enumerate() {
+            *pixel = image::Rgba([200, 0, 0, alphas[(i % 4) * 2 + i / 4]]);
+        }
+        let mut txtr = create_test_txtr(1, 1, 0, true);
+        txtr.set_image(DynamicImage::ImageRgba8(img));
+        txtr.calc_levels(false, 2);
+
+        let grid = txtr.grid_blocks(128);
+        let glyphs: String = grid.cells.iter().map(|cell| cell.glyph).collect();
+        assert_eq!(glyphs, "▀▀▄ ");
+        assert_eq!(grid.cells[0].bg, Some((200, 0, 0)));
+        assert_eq!(grid.cells[1].bg, None);
+        assert_eq!(grid.cells[2].fg, Some((200, 0, 0)));
+        assert_eq!(grid.cells[3].fg, None);
+        assert!(grid.to_ansi().contains("\x1b[49m"));
+
+        // A zero threshold keeps every pixel
+        assert!(txtr
+            .grid_blocks(0)
+            .cells
+            .iter()
+            .all(|cell| cell.glyph == '▀'));
+    }
+
+    #[test]
+    fn calc_levels_can_run_again() {
+        let mut txtr = create_test_txtr(3, 2, 100, false);
+        txtr.calc_levels(false, 2);
+        txtr.calc_levels(true, 4);
+        assert_eq!(txtr.pixels.len(), 8);
+        assert_eq!((txtr.min, txtr.max), (100, 100));
+    }
 }
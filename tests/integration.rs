@@ -38,6 +38,20 @@ fn basic_conversion_works() {
     assert!(!stdout.is_empty());
 }
 
+#[test]
+fn color_output_resets_every_line() {
+    let output = run_txtr(&[TEST_IMAGE, "-w", "10", "--color"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // As before the grid refactor: a reset on every line, and one more at the end
+    assert!(stdout.ends_with("\x1b[0m\n\x1b[0m"));
+    assert!(stdout.lines().all(|line| line.ends_with("\x1b[0m")));
+
+    // Blocks never had the extra reset
+    let output = run_txtr(&[TEST_IMAGE, "-w", "10", "--blocks"]);
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("\x1b[0m\n"));
+}
+
 #[test]
 fn empty_chars_rejected() {
     let output = run_txtr(&[TEST_IMAGE, "--chars", ""]);
@@ -242,3 +256,1065 @@ fn ramp_is_case_sensitive() {
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("unknown ramp"));
 }
+
+#[test]
+fn json_format_works() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "json", "--color", "-w", "10"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("{\"width\":10,"));
+    assert!(stdout.contains("\"cells\":["));
+    assert!(stdout.contains("\"fg\":["));
+    // Structured output must not contain terminal escapes
+    assert!(!stdout.contains('\x1b'));
+}
+
+#[test]
+fn invalid_format_warns() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "invalid_format", "-w", "10"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown format"));
+}
+
+#[test]
+fn source_formats_work() {
+    let expected = [
+        ("rust", "pub const TXTR_ART"),
+        ("c", "static const char TXTR_ART[]"),
+        ("python", "TXTR_ART = ("),
+        ("js", "export const TXTR_ART"),
+    ];
+    for (format, declaration) in expected {
+        let output = run_txtr(&[TEST_IMAGE, "--format", format, "--color", "-w", "10"]);
+        assert!(output.status.success(), "Format {} should work", format);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.starts_with(declaration));
+        // ANSI escapes must be encoded, never emitted raw
+        assert!(stdout.contains("\\x1b["));
+        assert!(!stdout.contains('\x1b'));
+    }
+}
+
+/// Art of control characters followed by hex digits, the case where a
+/// C hex escape needs the literal split; returns the text and the source
+fn render_hex_art(format: &str) -> (Vec<u8>, String) {
+    let args = [TEST_IMAGE, "--chars", "\x01A", "-p", "-l", "0", "-w", "10"];
+    let text = run_txtr(&args).stdout;
+    let output = run_txtr(&[&args[..], &["--format", format]].concat());
+    assert!(output.status.success());
+    (text, String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn rust_source_compiles_after_hex_escapes() {
+    let (text, source) = render_hex_art("rust");
+    let program = TempFile::new("art", "rs");
+    let main = "fn main() {\n    print!(\"{}\", TXTR_ART);\n}\n";
+    std::fs::write(program.path(), source + main).expect("Failed to write test program");
+    let binary = TempFile::new("art", "bin");
+    let status = Command::new("rustc")
+        .args(["--edition", "2021", "-o", binary.path(), program.path()])
+        .status()
+        .expect("Failed to run rustc");
+    assert!(status.success());
+    let output = Command::new(binary.path())
+        .output()
+        .expect("Failed to run test program");
+    assert_eq!(output.stdout, text);
+}
+
+#[test]
+fn js_source_parses_after_hex_escapes() {
+    let (text, source) = render_hex_art("js");
+    let program = source + "process.stdout.write(TXTR_ART);\n";
+    let Ok(output) = Command::new("node")
+        .args(["--input-type=module", "-e", &program])
+        .output()
+    else {
+        eprintln!("node not found, skipping");
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, text);
+}
+
+#[test]
+fn comment_prefix_works() {
+    let output = run_txtr(&[TEST_IMAGE, "--comment-prefix", "# ", "-w", "10"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().all(|line| line.starts_with("# ")));
+}
+
+#[test]
+fn escpos_text_format_works() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "escpos", "--printer-width", "32"]);
+    assert!(output.status.success());
+    // ESC @ then ESC t 0 (PC437)
+    assert!(output.stdout.starts_with(&[0x1b, b'@', 0x1b, b't', 0]));
+    let first_line = output.stdout[5..].split(|&b| b == b'\n').next().unwrap();
+    assert_eq!(first_line.len(), 32);
+}
+
+#[test]
+fn escpos_raster_format_works() {
+    let output = run_txtr(&[
+        TEST_IMAGE,
+        "--format",
+        "escpos",
+        "--raster",
+        "--dither",
+        "--printer-width",
+        "32",
+    ]);
+    assert!(output.status.success());
+    // GS v 0 with 32 columns * 12 dots / 8 = 48 bytes per row
+    assert!(output
+        .stdout
+        .starts_with(&[0x1b, b'@', 0x1b, b't', 0, 0x1d, b'v', b'0', 0, 48, 0]));
+}
+
+#[test]
+fn brf_format_works() {
+    let output = run_txtr(&[
+        TEST_IMAGE,
+        "--format",
+        "brf",
+        "--dither",
+        "--brf-page",
+        "20x10",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\r\n"));
+    assert!(stdout.contains('\x0c'));
+    // ASCII braille only, at most 20 cells per line and 10 lines per page
+    assert!(stdout.is_ascii());
+    for page in stdout.split_terminator('\x0c') {
+        let lines: Vec<&str> = page.split_terminator("\r\n").collect();
+        assert!(lines.len() <= 10);
+        assert!(lines.iter().all(|line| line.len() <= 20));
+    }
+}
+
+#[test]
+fn brf_invalid_options_rejected() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "brf", "--brf-page", "40by25"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn kitty_format_falls_back_to_blocks() {
+    let output = Command::new(get_binary_path())
+        .args([TEST_IMAGE, "--format", "kitty", "-w", "10"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("TERM", "dumb")
+        .env_remove("KITTY_WINDOW_ID")
+        .env_remove("TERM_PROGRAM")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("falling back to --blocks"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains('▀'));
+}
+
+#[test]
+fn kitty_format_in_kitty_terminal() {
+    let output = Command::new(get_binary_path())
+        .args([TEST_IMAGE, "--format", "kitty", "-w", "10"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("TERM", "xterm-kitty")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\x1b_Ga=T,f=100,q=2,c=10,"));
+}
+
+#[test]
+fn kitty_format_caps_image_size() {
+    use image::{Rgb, RgbImage};
+
+    // A 4:3 photo at -w 400 would be 4000x3000 pixels at full detail
+    let photo = TempFile::new("photo", "png");
+    RgbImage::from_fn(2400, 1800, |x, y| {
+        Rgb([(x % 256) as u8, (y % 256) as u8, 90])
+    })
+    .save(photo.path())
+    .expect("Failed to write test photo");
+    let output = Command::new(get_binary_path())
+        .args([photo.path(), "--format", "kitty", "-w", "400"])
+        .env("TERM", "xterm-kitty")
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\x1b_Ga=T,f=100,q=2,c=400,"));
+}
+
+#[test]
+fn iterm2_format_in_iterm2_terminal() {
+    let output = Command::new(get_binary_path())
+        .args([TEST_IMAGE, "--format", "iterm2", "-w", "10"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("TERM_PROGRAM", "iTerm.app")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\x1b]1337;File=inline=1;"));
+    assert!(stdout.contains(";width=10;"));
+}
+
+#[test]
+fn sixel_leaves_transparent_pixels_undrawn() {
+    let path = write_test_logo("sixel-logo");
+    let output = run_txtr(&[path.path(), "--format", "sixel", "-w", "4"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\x1bP0;1q"));
+    assert!(!stdout.ends_with("-\x1b\\\n"));
+    // The transparent black corners get no palette entry
+    assert!(!stdout.contains(";2;0;0;0"));
+}
+
+#[test]
+fn sixel_draws_nothing_for_a_transparent_image() {
+    use image::{Rgba, RgbaImage};
+
+    let path = TempFile::new("sixel-clear", "png");
+    RgbaImage::from_pixel(40, 40, Rgba([0, 0, 0, 0]))
+        .save(path.path())
+        .expect("Failed to write test PNG");
+    let output = run_txtr(&[path.path(), "--format", "sixel", "--dither", "-w", "4"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Only the header and raster size, with no colors or bands
+    assert!(stdout.starts_with("\x1bP0;1q\"1;1;40;"));
+    assert!(!stdout.contains('#'));
+    assert!(stdout.ends_with("\x1b\\\n"));
+}
+
+#[test]
+fn sixel_format_works() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "sixel", "--dither", "-w", "10"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // 10 columns at 10 pixels per cell
+    assert!(stdout.starts_with("\x1bP0;1q\"1;1;100;"));
+    assert!(stdout.trim_end().ends_with("\x1b\\"));
+}
+
+#[test]
+fn sixel_colors_limits_palette() {
+    let output = run_txtr(&[
+        TEST_IMAGE,
+        "--format",
+        "sixel",
+        "--sixel-colors",
+        "4",
+        "-w",
+        "10",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#3;2;"));
+    assert!(!stdout.contains("#4;2;"));
+
+    let output = run_txtr(&[TEST_IMAGE, "--format", "sixel", "--sixel-colors", "300"]);
+    assert!(!output.status.success());
+}
+
+fn run_txtr_with_stdin(args: &[&str], input: &[u8]) -> std::process::Output {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(get_binary_path())
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Commands that reject their arguments exit without reading stdin
+    child.stdin.take().unwrap().write_all(input).ok();
+    child
+        .wait_with_output()
+        .expect("Failed to wait for command")
+}
+
+#[test]
+fn convert_round_trips_color_output() {
+    let rendered = run_txtr(&[TEST_IMAGE, "--color", "-w", "20"]);
+    assert!(rendered.status.success());
+    let output = run_txtr_with_stdin(&["convert", "-"], &rendered.stdout);
+    assert!(output.status.success());
+    // Every line ends in a reset; the extra one after the last line is only
+    // written by character modes
+    let expected = rendered.stdout.strip_suffix(b"\x1b[0m").unwrap();
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn convert_document_formats_work() {
+    let input = b"\x1b[31mred\x1b[0m <tag>\n";
+    let html = run_txtr_with_stdin(&["convert", "-", "--format", "html"], input);
+    let html = String::from_utf8_lossy(&html.stdout);
+    assert!(html.contains("<span style=\"color:#cd0000\">red</span> &lt;tag&gt;"));
+
+    let svg = run_txtr_with_stdin(&["convert", "-", "--format", "svg"], input);
+    assert!(String::from_utf8_lossy(&svg.stdout).starts_with("<svg "));
+
+    let png = run_txtr_with_stdin(&["convert", "-", "--format", "png"], input);
+    assert!(png.stdout.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let json = run_txtr_with_stdin(&["convert", "-", "--format", "json"], input);
+    let json = String::from_utf8_lossy(&json.stdout);
+    assert!(json.contains("{\"glyph\":\"r\",\"level\":61,\"fg\":[205,0,0],\"bg\":null}"));
+}
+
+#[test]
+fn convert_requantizes_color_depth() {
+    let input = b"\x1b[38;2;255;135;0mx\n";
+    let output = run_txtr_with_stdin(&["convert", "-", "--colors", "256"], input);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b[38;5;208mx"));
+    let output = run_txtr_with_stdin(&["convert", "-", "--colors", "16"], input);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b[33mx"));
+}
+
+#[test]
+fn convert_reads_cp437_with_sauce() {
+    // Not valid UTF-8, so read as CP437
+    let mut input = b"\xb0\xdb\r\n".to_vec();
+    input.extend_from_slice(b"\x1aSAUCE00 metadata");
+    let output = run_txtr_with_stdin(&["convert", "-"], &input);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "░█\n");
+
+    // These bytes also happen to be valid UTF-8
+    let output = run_txtr_with_stdin(&["convert", "-", "--cp437"], b"\xdb\xb0");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "█░\n");
+}
+
+#[test]
+fn convert_wraps_long_lines() {
+    let output = run_txtr_with_stdin(&["convert", "-", "--wrap", "2"], b"abcde");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ab\ncd\ne \n");
+}
+
+#[test]
+fn convert_rejects_runaway_cursor_movement() {
+    for input in [&b"a\x1b[400000000Cb\n"[..], b"\x1b[99999;99999Hx"] {
+        let output = run_txtr_with_stdin(&["convert", "-"], input);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Error reading -"));
+    }
+}
+
+#[test]
+fn convert_rejects_image_only_formats() {
+    let output = run_txtr_with_stdin(&["convert", "-", "--format", "sixel"], b"x");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not supported by convert"));
+}
+
+#[test]
+fn html_format_works_for_images() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "html", "--color", "-w", "10"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("<!DOCTYPE html>"));
+    assert!(stdout.contains("<span style=\"color:#"));
+}
+
+/// A test fixture in the temp directory, removed when dropped
+struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    /// A path unique to this test run, e.g. txtr-logo-1234.png
+    fn new(name: &str, extension: &str) -> TempFile {
+        let file = format!("txtr-{}-{}.{}", name, std::process::id(), extension);
+        TempFile(std::env::temp_dir().join(file))
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().expect("temp path is not UTF-8")
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Write a small animated GIF: a white square moving across black, 3 frames of 200ms
+fn write_test_gif(name: &str) -> TempFile {
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, Rgba, RgbaImage};
+
+    let path = TempFile::new(name, "gif");
+    let file = std::fs::File::create(path.path()).expect("Failed to create test GIF");
+    let mut encoder = GifEncoder::new(file);
+    for step in 0..3 {
+        let img = RgbaImage::from_fn(30, 10, |x, _| {
+            if x / 10 == step {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        });
+        let delay = Delay::from_numer_denom_ms(200, 1);
+        encoder
+            .encode_frame(Frame::from_parts(img, 0, 0, delay))
+            .expect("Failed to encode test GIF");
+    }
+    path
+}
+
+#[test]
+fn asciicast_records_animation_frames() {
+    let gif = write_test_gif("asciicast");
+    let output = run_txtr(&[gif.path(), "--format", "asciicast", "-w", "15"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "{\"version\":2,\"width\":15,\"height\":5}");
+    // Three frames plus the closing event
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].starts_with("[0.000, \"o\", "));
+    assert!(lines[2].starts_with("[0.200, \"o\", "));
+    assert!(lines[3].starts_with("[0.400, \"o\", "));
+    assert!(lines[4].starts_with("[0.600, \"o\", "));
+    assert_ne!(lines[1], lines[2]);
+}
+
+#[test]
+fn asciicast_still_image_is_single_frame() {
+    let output = run_txtr(&[TEST_IMAGE, "--format", "asciicast", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("{\"version\":2,\"width\":20,"));
+    assert_eq!(stdout.lines().count(), 3);
+}
+
+#[test]
+fn gif_format_keeps_frames_and_delays() {
+    let input = write_test_gif("gif");
+    let output = run_txtr(&[input.path(), "--format", "gif", "-w", "15", "--blocks"]);
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"GIF89a"));
+
+    use image::AnimationDecoder;
+    let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(output.stdout)).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 3);
+    // 15 columns of the 8x13 font; 5 pixel rows make 3 rows of half blocks
+    assert_eq!(frames[0].buffer().dimensions(), (120, 39));
+    assert_eq!(frames[1].delay().numer_denom_ms(), (200, 1));
+}
+
+#[test]
+fn braille_mode_works() {
+    let output = run_txtr(&[TEST_IMAGE, "--braille", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        assert_eq!(line.chars().count(), 20);
+        assert!(line.chars().all(|c| ('\u{2800}'..='\u{28ff}').contains(&c)));
+    }
+}
+
+#[test]
+fn braille_color_and_dither_work() {
+    let output = run_txtr(&[TEST_IMAGE, "--braille", "--color", "--dither", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;"));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn braille_with_ramp_warns() {
+    let output = run_txtr(&[TEST_IMAGE, "--braille", "--ramp", "dense", "-w", "10"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--braille mode ignores: --ramp"));
+}
+
+#[test]
+fn braille_conflicts_with_blocks() {
+    let output = run_txtr(&[TEST_IMAGE, "--braille", "--blocks"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn mosaic_modes_work() {
+    for kind in ["quadrant", "sextant", "octant"] {
+        let output = run_txtr(&[TEST_IMAGE, "--mosaic", kind, "-w", "20"]);
+        assert!(output.status.success(), "--mosaic {} failed", kind);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // Two colors per cell, and the same number of rows as --blocks
+        assert!(stdout.contains("\x1b[38;2;") && stdout.contains("\x1b[48;2;"));
+        assert_eq!(stdout.lines().count(), 8, "--mosaic {}", kind);
+    }
+}
+
+#[test]
+fn mosaic_uses_sub_cell_glyphs() {
+    let output = run_txtr(&[TEST_IMAGE, "--mosaic", "sextant", "-w", "40"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout
+        .chars()
+        .any(|c| ('\u{1FB00}'..='\u{1FB3B}').contains(&c)));
+}
+
+#[test]
+fn invalid_mosaic_warns() {
+    let output = run_txtr(&[TEST_IMAGE, "--mosaic", "hexant", "-w", "10"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown mosaic 'hexant'"));
+}
+
+#[test]
+fn mosaic_conflicts_with_blocks() {
+    let output = run_txtr(&[TEST_IMAGE, "--mosaic", "octant", "--blocks"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn shape_match_uses_candidate_glyphs() {
+    let output = run_txtr(&[
+        TEST_IMAGE, "--match", "shape", "--glyphs", "ascii", "-w", "20",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Cells are twice as tall as wide, like every other mode
+    assert_eq!(stdout.lines().count(), 8);
+    assert!(stdout.is_ascii());
+    assert!(!stdout.contains("\x1b["));
+}
+
+#[test]
+fn shape_match_with_color_sets_both_colors() {
+    let output = run_txtr(&[TEST_IMAGE, "--match", "shape", "--color", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;") && stdout.contains("\x1b[48;2;"));
+}
+
+#[test]
+fn shape_match_rejects_empty_glyph_set() {
+    let output = run_txtr(&[TEST_IMAGE, "--match", "shape", "--glyphs", "emoji"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown glyph set 'emoji'"));
+    assert!(stderr.contains("--glyphs selected no glyphs"));
+}
+
+#[test]
+fn edges_draw_line_art() {
+    let output = run_txtr(&[TEST_IMAGE, "--edges", "-w", "40"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.chars().all(|c| " \n|-/\\_()".contains(c)),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains('|') || stdout.contains('-'));
+}
+
+#[test]
+fn edge_threshold_drops_edges() {
+    let strong = run_txtr(&[TEST_IMAGE, "--edges", "-w", "40", "--edge-threshold", "255"]);
+    let stdout = String::from_utf8_lossy(&strong.stdout);
+    assert!(stdout.trim().is_empty());
+}
+
+#[test]
+fn edges_conflict_with_blocks() {
+    let output = run_txtr(&[TEST_IMAGE, "--edges", "--blocks"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn edge_overlay_keeps_ramp_fill() {
+    let output = run_txtr(&[TEST_IMAGE, "--edge-overlay", "-w", "40", "--chars", "ab"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains('a') && stdout.contains('b'));
+    assert!(stdout.chars().any(|c| "|-/\\_()".contains(c)));
+}
+
+#[test]
+fn edge_color_colors_only_edges() {
+    let output = run_txtr(&[
+        TEST_IMAGE,
+        "--edge-overlay",
+        "-w",
+        "40",
+        "--edge-color",
+        "#ff8800",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;255;136;0m"));
+    assert!(!stdout.contains("\x1b[38;2;0;"));
+}
+
+#[test]
+fn invalid_edge_color_fails() {
+    let output = run_txtr(&[TEST_IMAGE, "--edges", "--edge-color", "orange"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("expected a hex color"));
+}
+
+#[test]
+fn bg_dim_sets_both_colors() {
+    let output = run_txtr(&[TEST_IMAGE, "--bg", "dim", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;") && stdout.contains("\x1b[48;2;"));
+}
+
+#[test]
+fn bg_only_prints_colored_spaces() {
+    let output = run_txtr(&[TEST_IMAGE, "--bg", "only", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[48;2;"));
+    assert!(!stdout.contains("\x1b[38;2;"));
+}
+
+#[test]
+fn bg_conflicts_with_blocks() {
+    let output = run_txtr(&[TEST_IMAGE, "--bg", "dim", "--blocks"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn attributes_add_sgr_shading() {
+    let output = run_txtr(&[TEST_IMAGE, "--attributes", "--ramp", "standard", "-w", "40"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[1m") || stdout.contains("\x1b[2m") || stdout.contains("\x1b[7m"));
+    assert!(!stdout.contains("\x1b[38;2;"));
+}
+
+#[test]
+fn attributes_bake_into_html_colors() {
+    let output = run_txtr(&[TEST_IMAGE, "--attributes", "--format", "html", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<span style=\"color:"));
+    assert!(!stdout.contains("\x1b["));
+}
+
+#[test]
+fn attributes_conflict_with_print_in_order() {
+    let output = run_txtr(&[TEST_IMAGE, "--attributes", "--print-in-order"]);
+    assert!(!output.status.success());
+}
+
+fn write_test_text(name: &str, text: &str) -> TempFile {
+    let path = TempFile::new(name, "txt");
+    std::fs::write(path.path(), text).expect("Failed to write test text");
+    path
+}
+
+#[test]
+fn text_source_flows_document() {
+    let path = write_test_text("speech", "Four score\nand seven years ago");
+    let output = run_txtr(&[
+        TEST_IMAGE,
+        "--text-source",
+        path.path(),
+        "-w",
+        "40",
+        "--level",
+        "0",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Every pixel is lit, so the text reads straight through
+    assert!(
+        stdout.starts_with("Four score and seven years agoFour score"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn text_source_collapses_whitespace() {
+    let path = write_test_text("spaces", "a\n\n  b");
+    let args = [
+        TEST_IMAGE,
+        "--text-source",
+        path.path(),
+        "--collapse-whitespace",
+        "-w",
+        "8",
+        "-l",
+        "0",
+    ];
+    let output = run_txtr(&args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("a ba ba "), "{}", stdout);
+}
+
+#[test]
+fn text_source_rejects_blank_file() {
+    let path = write_test_text("blank", " \n\t");
+    let output = run_txtr(&[TEST_IMAGE, "--text-source", path.path()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("has no text"));
+}
+
+#[test]
+fn keep_words_requires_text_source() {
+    let output = run_txtr(&[TEST_IMAGE, "--keep-words"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn emoji_mode_halves_columns() {
+    let output = run_txtr(&[TEST_IMAGE, "--emoji", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first = stdout.lines().next().unwrap();
+    assert_eq!(first.chars().count(), 10);
+    assert!(first.chars().all(|c| c as u32 > 0x2000));
+    assert!(!stdout.contains("\x1b["));
+}
+
+#[test]
+fn emoji_rejects_raster_formats() {
+    for format in ["png", "kitty", "iterm2", "sixel"] {
+        let output = run_txtr(&[TEST_IMAGE, "--emoji", "--format", format]);
+        assert!(!output.status.success(), "--format {}", format);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("can't draw emoji"));
+    }
+}
+
+/// A red disc on a fully transparent square
+fn write_test_logo(name: &str) -> TempFile {
+    use image::{Rgba, RgbaImage};
+
+    let path = TempFile::new(name, "png");
+    let img = RgbaImage::from_fn(40, 40, |x, y| {
+        let (dx, dy) = (x as i32 - 20, y as i32 - 20);
+        if dx * dx + dy * dy < 144 {
+            Rgba([220, 0, 0, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    img.save(path.path()).expect("Failed to write test PNG");
+    path
+}
+
+#[test]
+fn blocks_leave_transparent_pixels_to_terminal() {
+    let path = write_test_logo("logo");
+    let output = run_txtr(&[path.path(), "--blocks", "-w", "20"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // No black rectangle behind the disc: corners are plain spaces
+    assert!(!stdout.contains("\x1b[38;2;0;0;0m"));
+    assert!(stdout.lines().next().unwrap().starts_with(' '));
+    assert!(stdout.contains("\x1b[38;2;220;0;0m"));
+
+    // A zero threshold keeps the old opaque rendering
+    let args = [
+        path.path(),
+        "--blocks",
+        "-w",
+        "20",
+        "--alpha-threshold",
+        "0",
+    ];
+    let output = run_txtr(&args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;0;0;0m"));
+}
+
+#[test]
+fn level_accepts_auto_and_adaptive() {
+    for level in ["auto", "adaptive", "90"] {
+        let output = run_txtr(&[TEST_IMAGE, "-p", "-l", level, "-w", "20"]);
+        assert!(output.status.success(), "--level {}", level);
+    }
+    let output = run_txtr(&[TEST_IMAGE, "-p", "-l", "bright"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("auto or adaptive"));
+}
+
+#[test]
+fn two_tone_prints_two_glyphs() {
+    let output = run_txtr(&[TEST_IMAGE, "--two-tone", "#.", "-l", "auto", "-w", "30"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.chars().all(|c| c == '#' || c == '.' || c == '\n'));
+    assert!(stdout.contains('#') && stdout.contains('.'));
+}
+
+#[test]
+fn two_tone_needs_two_chars() {
+    let output = run_txtr(&[TEST_IMAGE, "--two-tone", "#"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("exactly 2 characters"));
+}
+
+#[test]
+fn mask_blanks_outside_cells() {
+    use image::{GrayImage, Luma};
+
+    // Left half is the subject
+    let path = TempFile::new("mask", "png");
+    GrayImage::from_fn(40, 40, |x, _| Luma([if x < 20 { 255 } else { 0 }]))
+        .save(path.path())
+        .expect("Failed to write test mask");
+    let output = run_txtr(&[TEST_IMAGE, "-w", "20", "--mask", path.path()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().all(|line| line[10..].trim().is_empty()));
+    assert!(stdout.lines().any(|line| !line[..10].trim().is_empty()));
+
+    let args = [
+        TEST_IMAGE,
+        "-w",
+        "20",
+        "--mask",
+        path.path(),
+        "--outside-chars",
+        "*",
+    ];
+    let output = run_txtr(&args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout
+        .lines()
+        .all(|line| line[10..].chars().all(|c| c == '*')));
+}
+
+#[test]
+fn mask_outside_colors_only_the_rest() {
+    let path = write_test_logo("mask-alpha");
+    let args = [
+        path.path(),
+        "-w",
+        "20",
+        "--mask",
+        "alpha",
+        "--outside-chars",
+        ".",
+        "--outside-color",
+        "on",
+    ];
+    let output = run_txtr(&args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Transparent corners are colored dots, the opaque disc keeps the plain ramp
+    assert!(stdout.starts_with("\x1b[38;2;0;0;0m."));
+    assert!(!stdout.contains("\x1b[38;2;220;0;0m"));
+}
+
+#[test]
+fn outside_options_require_mask() {
+    let output = run_txtr(&[TEST_IMAGE, "--outside-ramp", "dense"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn lines_trace_boxes() {
+    use image::{GrayImage, Luma};
+
+    // A white frame on black, like a window in a UI screenshot
+    let path = TempFile::new("frame", "png");
+    GrayImage::from_fn(40, 20, |x, y| {
+        let frame = (4..36).contains(&x) && (4..16).contains(&y);
+        let inside = (6..34).contains(&x) && (6..14).contains(&y);
+        Luma([if frame && !inside { 255 } else { 0 }])
+    })
+    .save(path.path())
+    .expect("Failed to write test frame");
+
+    let output = run_txtr(&[path.path(), "-w", "40", "-f", "1", "--lines", "heavy"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for c in ['┏', '┓', '┗', '┛', '━', '┃'] {
+        assert!(stdout.contains(c), "missing {} in\n{}", c, stdout);
+    }
+    let box_drawing = '\u{2500}'..='\u{257f}';
+    assert!(stdout
+        .chars()
+        .all(|c| c == ' ' || c == '\n' || box_drawing.contains(&c)));
+}
+
+#[test]
+fn lines_conflicts_with_edges() {
+    let output = run_txtr(&[TEST_IMAGE, "--lines", "light", "--edges"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn caption_goes_below_the_art() {
+    let args = [
+        TEST_IMAGE,
+        "-w",
+        "40",
+        "--caption",
+        "Hi",
+        "--caption-font",
+        "banner",
+    ];
+    let output = run_txtr(&args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    // Twelve rows of '#' letters under the art, centered in its 40 columns
+    let caption = &lines[lines.len() - 12..];
+    assert!(caption.iter().all(|line| line.chars().count() == 40));
+    assert!(caption.iter().any(|line| line.trim().starts_with('#')));
+    assert!(lines[..lines.len() - 12]
+        .iter()
+        .all(|line| !line.starts_with("    #")));
+}
+
+#[test]
+fn caption_loads_flf_files() {
+    let mut font = String::from("flf2a$ 1 1 3 -1 0\n");
+    for c in (' '..='~').chain("ÄÖÜäöüß".chars()) {
+        font.push_str(&format!(
+            "{}@@\n",
+            if c == ' ' {
+                '$'
+            } else {
+                c.to_ascii_lowercase()
+            }
+        ));
+    }
+    let path = write_test_text("font", &font);
+    let args = [
+        TEST_IMAGE,
+        "--caption",
+        "TXTR",
+        "--caption-font",
+        path.path(),
+    ];
+    let args = [&args[..], &["--caption-position", "above"]].concat();
+    let output = run_txtr(&args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().next().unwrap().trim(), "txtr");
+}
+
+#[test]
+fn caption_gradient_colors_letters() {
+    let args = [
+        TEST_IMAGE,
+        "-w",
+        "30",
+        "--caption",
+        "A",
+        "--caption-gradient",
+    ];
+    let output = run_txtr(&args);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b[38;2;"));
+}
+
+#[test]
+fn empty_caption_is_ignored() {
+    let plain = run_txtr(&[TEST_IMAGE, "-w", "20"]);
+    for caption in ["", " \n "] {
+        let output = run_txtr(&[TEST_IMAGE, "-w", "20", "--caption", caption]);
+        assert!(output.status.success());
+        assert_eq!(output.stdout, plain.stdout);
+    }
+}
+
+#[test]
+fn caption_unknown_font_fails() {
+    let output = run_txtr(&[TEST_IMAGE, "--caption", "x", "--caption-font", "standard"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("bundled fonts: block, braille, banner"));
+}
+
+#[test]
+fn several_files_make_a_contact_sheet() {
+    let output = run_txtr(&[TEST_IMAGE, TEST_IMAGE, TEST_IMAGE, "-w", "40", "--border"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    // Two thumbnails of 17 columns plus borders, two gutter columns between
+    assert!(lines.iter().all(|line| line.chars().count() == 40));
+    assert_eq!(lines[0].matches("castle.jpg").count(), 2);
+    assert_eq!(
+        lines[1],
+        format!("┌{}┐  ┌{}┐", "─".repeat(17), "─".repeat(17))
+    );
+    assert_eq!(stdout.matches("castle.jpg").count(), 3);
+}
+
+#[test]
+fn grid_limits_the_sheet() {
+    let output = run_txtr(&[
+        TEST_IMAGE, TEST_IMAGE, TEST_IMAGE, "-w", "30", "--grid", "2x1",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("castle.jpg").count(), 2);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("shows the first 2 of 3 images"));
+}
+
+#[test]
+fn contact_sheet_rejects_image_formats() {
+    let output = run_txtr(&[TEST_IMAGE, TEST_IMAGE, "--format", "sixel"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("can't show a contact sheet"));
+}
+
+#[test]
+fn separate_labels_aligned_panels() {
+    let output = run_txtr(&[TEST_IMAGE, "-w", "62", "--separate", "rgba"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    for channel in ["red", "green", "blue", "alpha"] {
+        assert!(lines[0].contains(channel), "{}", lines[0]);
+    }
+    // Four 14-column panels with 2-column gutters, every row the same width
+    assert!(lines.iter().all(|line| line.chars().count() == 62));
+    assert!(lines[1..].iter().all(|line| &line[14..16] == "  "));
+}
+
+#[test]
+fn separate_needs_a_single_image() {
+    let output = run_txtr(&[TEST_IMAGE, TEST_IMAGE, "--separate", "hsv"]);
+    assert!(!output.status.success());
+    let output = run_txtr(&[TEST_IMAGE, "--separate", "hsv", "--encoder", "red"]);
+    assert!(!output.status.success());
+}